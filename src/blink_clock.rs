@@ -0,0 +1,239 @@
+use lotus_rt::{spawn, wait};
+use lotus_script::{message::MessageMeta, prelude::MessageType, time::delta};
+
+use lotus_rt_extra::shared::Shared;
+
+/// The clock wraps after this time. The wrap shortens one blink cycle at most.
+const CLOCK_PERIOD: f32 = 600.0;
+
+/// Time base for all blinking lights of the vehicle. Every blinker derives its phase
+/// from the clock instead of counting ticks, so large `delta` values do not make the
+/// phases drift and blinkers with the same interval blink together.
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub struct BlinkClock {
+    /// Seconds since the clock was restarted, wraps after `CLOCK_PERIOD`
+    pub time: f32,
+    /// Blinkers currently active
+    pub users: u32,
+}
+
+impl BlinkClock {
+    pub fn advance(&mut self, delta: f32) {
+        self.time = (self.time + delta) % CLOCK_PERIOD;
+    }
+
+    /// The first active blinker restarts the clock, so it starts with the lamp on
+    pub fn activate(&mut self) {
+        if self.users == 0 {
+            self.time = 0.0;
+        }
+        self.users += 1;
+    }
+
+    pub fn deactivate(&mut self) {
+        self.users = self.users.saturating_sub(1);
+    }
+
+    /// Phase of a blinker with the given period that is lit for `on_time` at the start of each period
+    pub fn is_on(&self, on_time: f32, period: f32) -> bool {
+        self.time % period < on_time
+    }
+
+    /// Like [`is_on`](Self::is_on), with a differing first cycle of `first_period` after the
+    /// clock was restarted. The regular cycles follow shifted by the first one.
+    pub fn is_on_with_first_cycle(
+        &self,
+        first_on_time: f32,
+        first_period: f32,
+        on_time: f32,
+        period: f32,
+    ) -> bool {
+        if self.time < first_period {
+            self.time < first_on_time
+        } else {
+            (self.time - first_period) % period < on_time
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone)]
+pub struct BlinkClockState {
+    pub clock: Shared<BlinkClock>,
+}
+
+/// Clock of the leading vehicle, taken over by the coupled vehicles
+#[derive(Debug, Default, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct BlinkClockSync {
+    pub time: f32,
+}
+
+impl MessageType for BlinkClockSync {
+    const MESSAGE_META: MessageMeta = MessageMeta::new("gt6n", "blink_clock_sync", None);
+}
+
+impl BlinkClockState {
+    pub fn is_on(&self, on_time: f32, period: f32) -> bool {
+        self.clock.get().is_on(on_time, period)
+    }
+
+    pub fn is_on_with_first_cycle(
+        &self,
+        first_on_time: f32,
+        first_period: f32,
+        on_time: f32,
+        period: f32,
+    ) -> bool {
+        self.clock
+            .get()
+            .is_on_with_first_cycle(first_on_time, first_period, on_time, period)
+    }
+
+    pub fn time(&self) -> f32 {
+        self.clock.get().time
+    }
+
+    /// Registers a blinker with the clock while it is active. `registered` keeps whether
+    /// the blinker is registered between calls.
+    pub fn track_user(&self, active: bool, registered: &mut bool) {
+        if active == *registered {
+            return;
+        }
+
+        let mut clock = self.clock.get();
+        if active {
+            clock.activate();
+        } else {
+            clock.deactivate();
+        }
+        self.clock.set(clock);
+
+        *registered = active;
+    }
+
+    /// Blinks with half the interval on while `active`
+    pub fn blinker(&self, active: Shared<bool>, interval: Shared<f32>) -> Shared<bool> {
+        let blink = Shared::new(false);
+        let b = blink.clone();
+        let clock = self.clone();
+
+        spawn(async move {
+            let mut registered = false;
+
+            loop {
+                let active = active.get();
+                clock.track_user(active, &mut registered);
+
+                let interval = interval.get();
+                b.set_only_on_change(active && clock.is_on(interval * 0.5, interval));
+
+                wait::next_tick().await;
+            }
+        });
+
+        blink
+    }
+
+    pub fn synchronise(&self, sync: BlinkClockSync) {
+        let mut clock = self.clock.get();
+        clock.time = sync.time % CLOCK_PERIOD;
+        self.clock.set(clock);
+    }
+
+    pub fn sync_message(&self) -> BlinkClockSync {
+        BlinkClockSync { time: self.time() }
+    }
+}
+
+pub fn add_blink_clock() -> BlinkClockState {
+    let channels = BlinkClockState::default();
+    let c = channels.clone();
+
+    spawn(async move {
+        loop {
+            let mut clock = c.clock.get();
+            clock.advance(delta());
+            c.clock.set(clock);
+
+            wait::next_tick().await;
+        }
+    });
+
+    channels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn large_delta_keeps_the_phase() {
+        let mut clock = BlinkClock::default();
+
+        clock.advance(0.3);
+        clock.advance(1000.0);
+
+        assert!((clock.time - 400.3).abs() < 1e-3);
+        assert!(clock.is_on(0.4, 0.5));
+        assert!(!clock.is_on(0.2, 0.4));
+    }
+
+    #[test]
+    fn clock_wraps_after_its_period() {
+        let mut clock = BlinkClock {
+            time: CLOCK_PERIOD - 0.1,
+            users: 0,
+        };
+
+        clock.advance(0.3);
+
+        assert!((clock.time - 0.2).abs() < 1e-3);
+    }
+
+    #[test]
+    fn first_user_starts_with_the_lamp_on() {
+        let mut clock = BlinkClock {
+            time: 123.6,
+            users: 0,
+        };
+
+        clock.activate();
+        assert!(clock.is_on(0.375, 0.75));
+
+        // Ein weiterer Blinker übernimmt die laufende Phase
+        clock.advance(0.5);
+        clock.activate();
+        assert_eq!(clock.users, 2);
+        assert!(!clock.is_on(0.375, 0.75));
+
+        clock.deactivate();
+        clock.deactivate();
+        clock.deactivate();
+        assert_eq!(clock.users, 0);
+
+        clock.advance(0.5);
+        clock.activate();
+        assert_eq!(clock.time, 0.0);
+    }
+
+    #[test]
+    fn first_cycle_shifts_the_regular_cycles() {
+        let mut clock = BlinkClock::default();
+        clock.activate();
+
+        let is_on = |clock: &BlinkClock| clock.is_on_with_first_cycle(0.2, 0.76, 0.32, 0.75);
+
+        assert!(is_on(&clock));
+        clock.advance(0.25);
+        assert!(!is_on(&clock));
+
+        // Der zweite Zyklus beginnt nach dem ersten
+        clock.advance(0.5);
+        assert!(!is_on(&clock));
+        clock.advance(0.1);
+        assert!(is_on(&clock));
+        clock.advance(0.3);
+        assert!(!is_on(&clock));
+        clock.advance(0.45);
+        assert!(is_on(&clock));
+    }
+}
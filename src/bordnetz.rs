@@ -0,0 +1,152 @@
+use lotus_rt::{spawn, wait};
+use lotus_script::{
+    log,
+    time::delta,
+    var::{get_var, set_var},
+};
+
+use lotus_rt_extra::shared::Shared;
+
+const NOMINAL_VOLTAGE: f32 = 24.0;
+const BATTERY_CAPACITY_AH: f32 = 120.0;
+/// Open-circuit voltage of the empty and the full battery
+const BATTERY_VOLTAGE_EMPTY: f32 = 21.0;
+const BATTERY_VOLTAGE_FULL: f32 = 25.5;
+const BATTERY_INTERNAL_RESISTANCE: f32 = 0.02;
+
+const CONVERTER_VOLTAGE: f32 = 27.6;
+const CONVERTER_MAX_CURRENT: f32 = 200.0;
+const BATTERY_MAX_CHARGE_CURRENT: f32 = 30.0;
+
+/// Control electronics and other permanent consumers
+const BASE_CURRENT: f32 = 6.0;
+/// Permanent consumers in parking mode
+const STANDBY_CURRENT: f32 = 1.5;
+
+/// Below this voltage the consumers start to dim
+const DIM_VOLTAGE: f32 = 22.0;
+/// Below this voltage the consumers are cut off to protect the battery
+const CUTOFF_VOLTAGE: f32 = 18.0;
+/// After a cutoff the consumers are reconnected once the converter charges above this voltage
+const RECONNECT_VOLTAGE: f32 = 20.0;
+/// Relative supply of the consumers just above the cutoff
+const DIM_MIN_SUPPLY: f32 = 0.6;
+
+#[derive(Default, Debug, Clone)]
+pub struct BordnetzState {
+    /// Battery main switch, disconnects the whole network
+    pub battery_on: Shared<bool>,
+    /// Line voltage is available at the auxiliary converter
+    pub line_voltage: Shared<bool>,
+    pub converter_active: Shared<bool>,
+    /// Parking mode: only the permanent consumers needed for waking up stay on
+    pub standby: Shared<bool>,
+    /// State of charge of the battery, 0.0 to 1.0
+    pub battery_charge: Shared<f32>,
+    /// Network voltage in volts
+    pub voltage_v: Shared<f32>,
+    /// Supply of the consumers relative to full supply, 0.0 to 1.0.
+    /// Dims on undervoltage and drops to 0.0 after the cutoff.
+    pub voltage: Shared<f32>,
+    /// Current draw of the consumer groups in amperes
+    pub current_lights: Shared<f32>,
+    pub current_mg: Shared<f32>,
+    pub current_doors: Shared<f32>,
+    pub current_lamps: Shared<f32>,
+    pub current_wiper: Shared<f32>,
+}
+
+pub fn add_bordnetz() -> BordnetzState {
+    let initial_charge = get_var::<f32>("Cfg_BatteryCharge");
+
+    let channels = BordnetzState {
+        battery_charge: Shared::new(if initial_charge > 0.0 {
+            initial_charge.min(1.0)
+        } else {
+            1.0
+        }),
+        ..Default::default()
+    };
+    let c = channels.clone();
+
+    c.converter_active
+        .loop_sound("Snd_Bordnetz_Umformer".to_string());
+
+    spawn(async move {
+        let mut cut_off = false;
+
+        loop {
+            let base_current = if c.standby.get() {
+                STANDBY_CURRENT
+            } else {
+                BASE_CURRENT
+            };
+            let load = base_current
+                + c.current_lights.get()
+                + c.current_mg.get()
+                + c.current_doors.get()
+                + c.current_lamps.get()
+                + c.current_wiper.get();
+
+            let charge = c.battery_charge.get();
+            let open_circuit_voltage =
+                BATTERY_VOLTAGE_EMPTY + (BATTERY_VOLTAGE_FULL - BATTERY_VOLTAGE_EMPTY) * charge;
+
+            let battery_on = c.battery_on.get();
+
+            c.converter_active
+                .set_only_on_change(battery_on && c.line_voltage.get());
+
+            // Positiver Strom lädt die Batterie
+            let (voltage_v, battery_current) = if !battery_on {
+                (0.0, 0.0)
+            } else if c.converter_active.get() {
+                let charge_current = ((CONVERTER_VOLTAGE - open_circuit_voltage)
+                    / BATTERY_INTERNAL_RESISTANCE)
+                    .min(BATTERY_MAX_CHARGE_CURRENT)
+                    .clamp(0.0, (CONVERTER_MAX_CURRENT - load).max(0.0));
+                (CONVERTER_VOLTAGE, charge_current)
+            } else {
+                let load = if cut_off { base_current } else { load };
+                (
+                    (open_circuit_voltage - load * BATTERY_INTERNAL_RESISTANCE).max(0.0),
+                    -load,
+                )
+            };
+
+            c.battery_charge.set_only_on_change(
+                (charge + battery_current * delta() / 3600.0 / BATTERY_CAPACITY_AH).clamp(0.0, 1.0),
+            );
+
+            if !battery_on {
+                cut_off = false;
+            } else if !cut_off && voltage_v < CUTOFF_VOLTAGE {
+                log::info!("Bordnetz: Unterspannung, Verbraucher abgeschaltet");
+                cut_off = true;
+            } else if cut_off && c.converter_active.get() && voltage_v > RECONNECT_VOLTAGE {
+                cut_off = false;
+            }
+
+            let supply = if cut_off || !battery_on {
+                0.0
+            } else if voltage_v < DIM_VOLTAGE {
+                DIM_MIN_SUPPLY
+                    + (1.0 - DIM_MIN_SUPPLY) * (voltage_v - CUTOFF_VOLTAGE)
+                        / (DIM_VOLTAGE - CUTOFF_VOLTAGE)
+            } else {
+                1.0
+            };
+
+            c.voltage_v.set_only_on_change(voltage_v);
+            c.voltage.set_only_on_change(supply.clamp(0.0, 1.0));
+
+            set_var("Bordnetz_Voltage", &voltage_v);
+            set_var("Bordnetz_Voltage_Rel", &(voltage_v / NOMINAL_VOLTAGE));
+            set_var("Battery_Charge", &c.battery_charge.get());
+
+            wait::next_tick().await;
+        }
+    });
+
+    channels
+}
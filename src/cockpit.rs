@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use lotus_rt_extra::{
     cockpit_simple::{
         button_inout, button_twosided_springloaded, std_button, step_switch, switch,
@@ -8,10 +10,37 @@ use lotus_rt_extra::{
     shared::Shared,
 };
 
-use crate::cockpit_types::{BlinkerSwitch, DoorSwitch, OutsideLightSwitch, RichtungswenderState};
+use crate::cockpit_types::{
+    BlinkerSwitch, DoorModeSwitch, DoorSwitch, OutsideLightSwitch, RichtungswenderState,
+};
+
+/// Indicator lamps of the cab that can fail, by variable name. The door lamps `A_LM_Door_{n}`
+/// and `A_LM_Door_{n}_Notentriegelung` come on top.
+const CAB_LAMPS: [&str; 18] = [
+    "A_LM_FSp",
+    "A_LM_Fernlicht",
+    "A_LM_BlinkerRechts",
+    "A_LM_BlinkerLinks",
+    "A_LM_Warnblinken",
+    "A_LM_DoorsClosed",
+    "A_LM_Haltewunsch",
+    "A_LM_Kinderwagen",
+    "A_LM_Rollstuhl",
+    "A_LM_Schienenbremse",
+    "A_LM_Sifa",
+    "A_LM_Sprechstelle",
+    "A_LM_Hauptschalter",
+    "A_LM_Notstart",
+    "A_LM_Notablegen",
+    "A_LM_Notentriegelung",
+    "A_LM_Wartung",
+    "A_LM_Stoerung",
+];
 
 #[derive(Debug, Clone)]
 pub struct CockpitState {
+    pub batterie: Shared<bool>,
+    pub schluessel: Shared<bool>,
     pub lightcheck: Shared<bool>,
     pub richtungswender: Shared<RichtungswenderState>,
     pub sollwertgeber: Shared<f32>,
@@ -21,16 +50,26 @@ pub struct CockpitState {
     pub sanden: Shared<bool>,
     pub mg_bremse: Shared<bool>,
     pub beleuchtung_aussen: Shared<OutsideLightSwitch>,
+    /// Spring-loaded headlight flasher
+    pub lichthupe: Shared<bool>,
     pub beleuchtung_fahrerraum: Shared<i8>,
     pub beleuchtung_fahrgastraum: Shared<bool>,
     pub blinker: Shared<BlinkerSwitch>,
     pub warnblinker: Shared<ButtonInOutState>,
     pub klingel: Shared<bool>,
     pub tueren: Shared<DoorSwitch>,
+    pub tueren_automatik: Shared<bool>,
+    pub tuer_modus: Vec<Shared<DoorModeSwitch>>,
+    pub zwangsschliessen: Shared<bool>,
+    pub zwangsschliessen_tuer: Vec<Shared<bool>>,
+    pub notentriegelung_reset: Shared<bool>,
+    pub notstart: Shared<ButtonInOutState>,
     pub kinderwagen: Shared<bool>,
     pub rollstuhl: Shared<bool>,
+    pub rampe: Shared<bool>,
     pub sifa: Shared<bool>,
     pub scheibenwischer: Shared<i8>,
+    pub waschanlage: Shared<bool>,
     pub sprechstelle: Shared<ButtonTwoSidedSpringLoadedState>,
     pub zugbildung: Shared<i8>,
     pub lm_check: Shared<bool>,
@@ -40,6 +79,7 @@ pub struct CockpitState {
     pub lm_blinker_links: Shared<bool>,
     pub lm_warnblinker: Shared<bool>,
     pub lm_doors_closed: Shared<bool>,
+    pub lm_tueren: Vec<Shared<bool>>,
     pub lm_haltewunsch: Shared<bool>,
     pub lm_kinderwagen: Shared<bool>,
     pub lm_rollstuhl: Shared<bool>,
@@ -49,6 +89,24 @@ pub struct CockpitState {
     pub lm_hauptschalter: Shared<bool>,
     pub lm_notstart: Shared<bool>,
     pub lm_notablegen: Shared<bool>,
+    pub lm_notentriegelung: Shared<bool>,
+    /// Notentriegelung per door
+    pub lm_tueren_notentriegelung: Vec<Shared<bool>>,
+    pub lm_wartung: Shared<bool>,
+    /// Failed exterior light
+    pub lm_stoerung: Shared<bool>,
+    /// Cab lamps by variable name
+    pub lamps: HashMap<String, CabLamp>,
+    /// Supply of the cab lamps, fed by the Bordnetz
+    pub voltage: Shared<f32>,
+}
+
+/// Indicator lamp of the cab. A failed lamp stays dark during the lamp test.
+#[derive(Debug, Clone, Default)]
+pub struct CabLamp {
+    pub failed: Shared<bool>,
+    /// The lamp is lit, including the lamp test
+    pub lit: Shared<bool>,
 }
 
 pub struct CockpitRearState {}
@@ -107,10 +165,22 @@ pub fn add_cockpit() -> CockpitState {
         )
     };
 
+    let lamps: HashMap<String, CabLamp> = CAB_LAMPS
+        .iter()
+        .map(|name| name.to_string())
+        .chain((0..4).map(|i| format!("A_LM_Door_{}", i + 1)))
+        .chain((0..4).map(|i| format!("A_LM_Door_{}_Notentriegelung", i + 1)))
+        .map(|name| (name, CabLamp::default()))
+        .collect();
+
     let std_lm = |variable: &str| -> Shared<bool> {
         let value = Shared::default();
+        let lamp = lamps.get(variable).cloned().unwrap_or_default();
         value
             .or(&lm_check)
+            .and(&lamp.failed.process(|f| !*f, true))
+            .forward(&lamp.lit);
+        lamp.lit
             .to_float()
             .multiply(&voltage_r)
             .var_writer(variable);
@@ -128,8 +198,36 @@ pub fn add_cockpit() -> CockpitState {
         klingel: gt6n_button("Bell1", "A_CP_TS_Klingel"),
         kinderwagen: gt6n_button("ResetBuggy", "A_CP_TS_KiWa"),
         rollstuhl: gt6n_button("ResetWheelchair", "A_CP_TS_Rolli"),
+        rampe: gt6n_button("RampToggle", "A_CP_TS_Rampe"),
         sifa: gt6n_button("HoldToRun_Btn", "A_CP_TS_SiFa"),
         lightcheck: gt6n_button("Lightcheck", "A_CP_TS_Lampentest"),
+        lichthupe: gt6n_button("HeadlightFlasher", "A_CP_TS_Lichthupe"),
+        waschanlage: gt6n_button("WasherPump", "A_CP_TS_Waschanlage"),
+        zwangsschliessen: gt6n_button("DoorsForceClose", "A_CP_TS_Zwangsschliessen"),
+        zwangsschliessen_tuer: (0..4)
+            .map(|i| {
+                gt6n_button(
+                    format!("DoorForceClose{}", i + 1).as_str(),
+                    format!("A_CP_TS_Zwangsschliessen_{}", i + 1).as_str(),
+                )
+            })
+            .collect(),
+        notentriegelung_reset: gt6n_button("DoorEmergencyReset", "A_CP_TS_Notentriegelung"),
+
+        batterie: switch(
+            SwitchProperties::builder()
+                .toggle_event("BatteryMainSwitchToggle")
+                .animation_var("A_CP_SW_Batterie")
+                .sound_switch("Snd_CP_A_Switch")
+                .build(),
+        ),
+        schluessel: switch(
+            SwitchProperties::builder()
+                .toggle_event("CabKeyToggle")
+                .animation_var("A_CP_Schluessel")
+                .sound_switch("Snd_CP_A_Key")
+                .build(),
+        ),
 
         pantograph: button_twosided_springloaded(
             ButtonTwoSidedSpringLoadedProperties::builder()
@@ -182,6 +280,14 @@ pub fn add_cockpit() -> CockpitState {
             None::<fn() -> BlinkerSwitch>,
             None::<fn() -> BlinkerSwitch>,
         ),
+        notstart: button_inout(
+            ButtonProperties::builder()
+                .input_event("EmergencyStartToggle")
+                .animation_var("A_CP_TS_Notstart")
+                .sound_on("Snd_CP_A_BtnDn")
+                .sound_off("Snd_CP_A_BtnUp")
+                .build(),
+        ),
         warnblinker: button_inout(
             ButtonProperties::builder()
                 .input_event("IndicatorWarn")
@@ -225,6 +331,31 @@ pub fn add_cockpit() -> CockpitState {
             None::<fn() -> DoorSwitch>,
         ),
 
+        tueren_automatik: switch(
+            SwitchProperties::builder()
+                .toggle_event("DoorsAutoReleaseToggle")
+                .animation_var("A_CP_SW_TuerAutomatik")
+                .sound_switch("Snd_CP_A_Switch")
+                .build(),
+        ),
+
+        tuer_modus: (0..4)
+            .map(|i| {
+                step_switch::<DoorModeSwitch>(
+                    StepSwitchProperties::builder()
+                        .input_event_minus(format!("DoorMode{}Minus", i + 1))
+                        .input_event_plus(format!("DoorMode{}Plus", i + 1))
+                        .position_min(DoorModeSwitch::Close)
+                        .position_max(DoorModeSwitch::Open)
+                        .animation_var(format!("A_CP_SW_Tuer_{}", i + 1))
+                        .sound("Snd_CP_A_Switch")
+                        .build(),
+                    None::<fn() -> DoorModeSwitch>,
+                    None::<fn() -> DoorModeSwitch>,
+                )
+            })
+            .collect(),
+
         scheibenwischer: step_switch(
             StepSwitchProperties::builder()
                 .input_event_minus("WiperMinus")
@@ -268,6 +399,9 @@ pub fn add_cockpit() -> CockpitState {
         lm_warnblinker: std_lm("A_LM_Warnblinken"),
 
         lm_doors_closed: std_lm("A_LM_DoorsClosed"),
+        lm_tueren: (0..4)
+            .map(|i| std_lm(format!("A_LM_Door_{}", i + 1).as_str()))
+            .collect(),
         lm_haltewunsch: std_lm("A_LM_Haltewunsch"),
         lm_kinderwagen: std_lm("A_LM_Kinderwagen"),
         lm_rollstuhl: std_lm("A_LM_Rollstuhl"),
@@ -278,6 +412,14 @@ pub fn add_cockpit() -> CockpitState {
         lm_hauptschalter: std_lm("A_LM_Hauptschalter"),
         lm_notstart: std_lm("A_LM_Notstart"),
         lm_notablegen: std_lm("A_LM_Notablegen"),
+        lm_notentriegelung: std_lm("A_LM_Notentriegelung"),
+        lm_tueren_notentriegelung: (0..4)
+            .map(|i| std_lm(format!("A_LM_Door_{}_Notentriegelung", i + 1).as_str()))
+            .collect(),
+        lm_wartung: std_lm("A_LM_Wartung"),
+        lm_stoerung: std_lm("A_LM_Stoerung"),
+        lamps,
+        voltage: voltage_r,
     };
 
     state.lm_doors_closed.trigger_sound("Snd_CP_A_DoorsClosed");
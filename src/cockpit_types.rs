@@ -229,3 +229,55 @@ impl StepSwitchPosition for DoorSwitch {
         ]
     }
 }
+
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DoorModeSwitch {
+    Close,
+    #[default]
+    Automatic,
+    Open,
+}
+
+impl StepSwitchPosition for DoorModeSwitch {
+    fn next(&self) -> Self {
+        match self {
+            DoorModeSwitch::Close => DoorModeSwitch::Automatic,
+            DoorModeSwitch::Automatic => DoorModeSwitch::Open,
+            DoorModeSwitch::Open => DoorModeSwitch::Open,
+        }
+    }
+
+    fn previous(&self) -> Self {
+        match self {
+            DoorModeSwitch::Close => DoorModeSwitch::Close,
+            DoorModeSwitch::Automatic => DoorModeSwitch::Close,
+            DoorModeSwitch::Open => DoorModeSwitch::Automatic,
+        }
+    }
+
+    fn generate_with_this_sound(
+        sound: &Option<String>,
+        _: Self,
+        _: Self,
+    ) -> Vec<CockpitSoundAndVarSetState<Self, f32>> {
+        vec![
+            CockpitSoundAndVarSetState {
+                input: DoorModeSwitch::Close,
+                output: -1.0,
+                sound: sound.clone(),
+            },
+            CockpitSoundAndVarSetState {
+                input: DoorModeSwitch::Automatic,
+                output: 0.0,
+                sound: sound.clone(),
+            },
+            CockpitSoundAndVarSetState {
+                input: DoorModeSwitch::Open,
+                output: 1.0,
+                sound: sound.clone(),
+            },
+        ]
+    }
+}
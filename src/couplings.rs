@@ -1,3 +1,313 @@
-pub fn add_coupling() {
-    
-}
\ No newline at end of file
+use crate::{
+    blink_clock::{BlinkClockState, BlinkClockSync},
+    lights::{BlinkerState, VehicleEnd},
+};
+use lotus_rt::{spawn, wait};
+use lotus_rt_extra::{doors::DoorControlMode, shared::Shared};
+
+use lotus_script::{
+    message::{send_message, Coupling, MessageMeta, MessageTarget},
+    prelude::MessageType,
+    time::delta,
+};
+
+/// Interval in which the coupling messages are repeated even without a change
+const HEARTBEAT_TIME: f32 = 0.25;
+/// Messages older than this are dropped, e.g. after uncoupling
+const TIMEOUT: f32 = 1.0;
+
+const TARGETS: [MessageTarget; 2] = [
+    MessageTarget::AcrossCoupling {
+        coupling: Coupling::Front,
+        cascade: true,
+    },
+    MessageTarget::AcrossCoupling {
+        coupling: Coupling::Rear,
+        cascade: true,
+    },
+];
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum DoorOverride {
+    #[default]
+    Automatic,
+    Open,
+    Close,
+}
+
+impl From<DoorControlMode> for DoorOverride {
+    fn from(value: DoorControlMode) -> Self {
+        match value {
+            DoorControlMode::Automatic => DoorOverride::Automatic,
+            DoorControlMode::Open => DoorOverride::Open,
+            DoorControlMode::Close => DoorOverride::Close,
+        }
+    }
+}
+
+impl From<DoorOverride> for DoorControlMode {
+    fn from(value: DoorOverride) -> Self {
+        match value {
+            DoorOverride::Automatic => DoorControlMode::Automatic,
+            DoorOverride::Open => DoorControlMode::Open,
+            DoorOverride::Close => DoorControlMode::Close,
+        }
+    }
+}
+
+/// Door release, forced closing and door 1 override of the leading cab
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct DoorCommand {
+    pub released: bool,
+    pub open_all: bool,
+    pub force_close: bool,
+    pub door_1_override: DoorOverride,
+}
+
+impl MessageType for DoorCommand {
+    const MESSAGE_META: MessageMeta = MessageMeta::new("gt6n", "door_command", None);
+}
+
+/// Door loop of a coupled vehicle without active cab
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct DoorLoop {
+    pub all_closed: bool,
+}
+
+impl MessageType for DoorLoop {
+    const MESSAGE_META: MessageMeta = MessageMeta::new("gt6n", "door_loop", None);
+}
+
+/// Sent across both couplings without cascading, so the neighbours know they are coupled.
+/// The receiving end follows from the coupling the message arrived through, so the vehicles
+/// may be coupled either way round.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CouplingPresence;
+
+impl MessageType for CouplingPresence {
+    const MESSAGE_META: MessageMeta = MessageMeta::new("gt6n", "coupling_presence", None);
+}
+
+impl From<Coupling> for VehicleEnd {
+    fn from(coupling: Coupling) -> Self {
+        match coupling {
+            Coupling::Front => VehicleEnd::A,
+            Coupling::Rear => VehicleEnd::B,
+        }
+    }
+}
+
+/// Lights of the leading vehicle mirrored by the coupled vehicles
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct LightingSync {
+    pub tail_lights: bool,
+    pub brems: bool,
+    pub rueckfahr: bool,
+    pub gefahrbremsung: bool,
+    pub blinker: BlinkerState,
+}
+
+impl MessageType for LightingSync {
+    const MESSAGE_META: MessageMeta = MessageMeta::new("gt6n", "lighting_sync", None);
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CouplingRole {
+    /// Not coupled and no active cab
+    #[default]
+    Alone,
+    Leading,
+    Middle,
+    Trailing,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CouplingState {
+    /// This vehicle has the active cab and leads the coupled vehicles
+    pub lead: Shared<bool>,
+    /// Sent to the coupled vehicles while leading
+    pub door_command: Shared<DoorCommand>,
+    /// Received from the leading vehicle
+    pub remote_door_command: Shared<Option<DoorCommand>>,
+    /// Sent to the leading vehicle while not leading
+    pub doors_closed: Shared<bool>,
+    /// All doors of the coupled vehicles are closed
+    pub remote_doors_closed: Shared<bool>,
+    pub coupled_a: Shared<bool>,
+    pub coupled_b: Shared<bool>,
+    pub role: Shared<CouplingRole>,
+    /// Sent to the coupled vehicles while leading
+    pub lighting: Shared<LightingSync>,
+    /// Received from the leading vehicle
+    pub remote_lighting: Shared<Option<LightingSync>>,
+    /// Sent to the coupled vehicles while leading, replaced by the leading vehicle's clock otherwise
+    pub blink_clock: BlinkClockState,
+    remote_door_command_age: Shared<f32>,
+    remote_doors_open_age: Shared<f32>,
+    coupled_a_age: Shared<f32>,
+    coupled_b_age: Shared<f32>,
+    remote_lighting_age: Shared<f32>,
+}
+
+impl CouplingState {
+    pub fn receive_door_command(&self, command: DoorCommand) {
+        if !self.lead.get() {
+            self.remote_door_command.set_only_on_change(Some(command));
+            self.remote_door_command_age.set(0.0);
+        }
+    }
+
+    /// `coupling` is the coupling of this vehicle the presence arrived through
+    pub fn receive_presence(&self, coupling: Coupling) {
+        match VehicleEnd::from(coupling) {
+            VehicleEnd::A => {
+                self.coupled_a.set_only_on_change(true);
+                self.coupled_a_age.set(0.0);
+            }
+            VehicleEnd::B => {
+                self.coupled_b.set_only_on_change(true);
+                self.coupled_b_age.set(0.0);
+            }
+        }
+    }
+
+    pub fn receive_lighting(&self, lighting: LightingSync) {
+        if !self.lead.get() {
+            self.remote_lighting.set_only_on_change(Some(lighting));
+            self.remote_lighting_age.set(0.0);
+        }
+    }
+
+    pub fn receive_blink_clock(&self, sync: BlinkClockSync) {
+        if !self.lead.get() {
+            self.blink_clock.synchronise(sync);
+        }
+    }
+
+    pub fn receive_door_loop(&self, door_loop: DoorLoop) {
+        // Eine einzige offene Tür im Zugverband genügt
+        if self.lead.get() && !door_loop.all_closed {
+            self.remote_doors_closed.set_only_on_change(false);
+            self.remote_doors_open_age.set(0.0);
+        }
+    }
+}
+
+pub fn add_coupling(blink_clock: BlinkClockState) -> CouplingState {
+    let state = CouplingState {
+        blink_clock,
+        remote_doors_closed: Shared::new(true),
+        remote_door_command_age: Shared::new(TIMEOUT),
+        remote_doors_open_age: Shared::new(TIMEOUT),
+        coupled_a_age: Shared::new(TIMEOUT),
+        coupled_b_age: Shared::new(TIMEOUT),
+        remote_lighting_age: Shared::new(TIMEOUT),
+        ..Default::default()
+    };
+    let s = state.clone();
+
+    spawn(async move {
+        let mut heartbeat = 0.0;
+        let mut prev_door_command = None;
+        let mut prev_doors_closed = None;
+        let mut prev_lighting = None;
+        let mut prev_blink_time = 0.0;
+
+        loop {
+            heartbeat += delta();
+            let heartbeat_due = heartbeat >= HEARTBEAT_TIME;
+            if heartbeat_due {
+                heartbeat = 0.0;
+            }
+
+            if heartbeat_due {
+                send_message(
+                    &CouplingPresence,
+                    [Coupling::Front, Coupling::Rear].map(|coupling| {
+                        MessageTarget::AcrossCoupling {
+                            coupling,
+                            cascade: false,
+                        }
+                    }),
+                );
+            }
+
+            if s.lead.get() {
+                let door_command = s.door_command.get();
+                if heartbeat_due || prev_door_command != Some(door_command) {
+                    send_message(&door_command, TARGETS);
+                }
+                prev_door_command = Some(door_command);
+                prev_doors_closed = None;
+
+                let lighting = s.lighting.get();
+                if heartbeat_due || prev_lighting != Some(lighting) {
+                    send_message(&lighting, TARGETS);
+                }
+                prev_lighting = Some(lighting);
+
+                // Die gekuppelten Fahrzeuge übernehmen die Blinkuhr, damit alle synchron blinken.
+                // Nach einem Neustart der Uhr sofort, sonst laufen die Blinker eine Weile versetzt.
+                let blink_time = s.blink_clock.time();
+                if heartbeat_due || blink_time < prev_blink_time {
+                    send_message(&s.blink_clock.sync_message(), TARGETS);
+                }
+                prev_blink_time = blink_time;
+            } else {
+                let doors_closed = s.doors_closed.get();
+                if heartbeat_due || prev_doors_closed != Some(doors_closed) {
+                    send_message(
+                        &DoorLoop {
+                            all_closed: doors_closed,
+                        },
+                        TARGETS,
+                    );
+                }
+                prev_doors_closed = Some(doors_closed);
+                prev_door_command = None;
+                prev_lighting = None;
+            }
+
+            for (coupled, coupled_age) in [
+                (&s.coupled_a, &s.coupled_a_age),
+                (&s.coupled_b, &s.coupled_b_age),
+            ] {
+                let age = coupled_age.get() + delta();
+                coupled_age.set(age);
+                if age > TIMEOUT {
+                    coupled.set_only_on_change(false);
+                }
+            }
+
+            let age = s.remote_lighting_age.get() + delta();
+            s.remote_lighting_age.set(age);
+            if age > TIMEOUT || s.lead.get() {
+                s.remote_lighting.set_only_on_change(None);
+            }
+
+            s.role
+                .set_only_on_change(match (s.lead.get(), s.coupled_a.get(), s.coupled_b.get()) {
+                    (true, _, _) => CouplingRole::Leading,
+                    (false, true, true) => CouplingRole::Middle,
+                    (false, true, false) | (false, false, true) => CouplingRole::Trailing,
+                    (false, false, false) => CouplingRole::Alone,
+                });
+
+            let age = s.remote_door_command_age.get() + delta();
+            s.remote_door_command_age.set(age);
+            if age > TIMEOUT || s.lead.get() {
+                s.remote_door_command.set_only_on_change(None);
+            }
+
+            let age = s.remote_doors_open_age.get() + delta();
+            s.remote_doors_open_age.set(age);
+            if age > TIMEOUT || !s.lead.get() {
+                s.remote_doors_closed.set_only_on_change(true);
+            }
+
+            wait::next_tick().await;
+        }
+    });
+
+    state
+}
@@ -1,20 +1,24 @@
-use lotus_script::log;
+use lotus_rt::{spawn, wait};
+use lotus_script::{
+    log,
+    message::MessageMeta,
+    prelude::MessageType,
+    time::delta,
+    var::{get_var, set_var},
+};
 
 use lotus_rt_extra::{
-    combined::{
-        blink_relais_multiple_entries, BlinkRelaisMultipleEntriesProperties,
-        BlinkRelaisWithLightAndSoundProperties, LightAndSoundVarPair,
-    },
     doors::{
         door_control, door_warning_outside_relay_with_stop_on_speed, DoorControlMode,
-        DoorControlProperties, DoorControlState, DoorWarningOutsideRelayWithStopOnSpeedProperties,
+        DoorControlProperties, DoorWarningOutsideRelayWithStopOnSpeedProperties,
         ElectricSlidingPlugDoorPairPositionState, ElectricSlidingPlugDoorPairProperties,
         ElectricSlidingPlugDoorPairState, ElectricSlidingPlugDoorPairTarget,
     },
     shared::Shared,
-    simple::BlinkRelaisProperties,
 };
 
+use crate::blink_clock::BlinkClockState;
+
 const PLUG_RADIUS: f32 = 0.06;
 const SHIFT_DISTANCE: f32 = 0.58;
 const FRICTION: f32 = 0.05;
@@ -24,105 +28,643 @@ const CLOSE_END_SPEED: f32 = 0.1;
 const CLOSE_START_END_CHANGE_POSITION: f32 = 0.2;
 const TRACTION_STIFTNESS: f32 = 4.0;
 
+const OUTSIDE_WARNING_MAX_SPEED: f32 = 3.0 / 3.6;
+
+/// Side of the vehicle the passenger doors are on
+pub const DOOR_SIDE: PlatformSide = PlatformSide::Right;
+
+/// Door cycles after which maintenance is due
+const MAINTENANCE_CYCLES: u32 = 20_000;
+/// Operating hours after which maintenance is due
+const MAINTENANCE_OPERATING_HOURS: u32 = 500;
+/// Door cycles after which the drive is fully worn
+const WEAR_CYCLES: f32 = 40_000.0;
+
+/// Time a passenger needs to pass the light barrier
+const PASSENGER_PASSAGE_TIME: f32 = 0.8;
+/// Gap between two passengers passing the same door
+const PASSENGER_GAP_TIME: f32 = 0.5;
+
+/// Door timing profiles of the operators on the map. The initial profile is taken from
+/// `Cfg_DoorTimingProfile`, a [`DoorTimingProfile`] message switches it at runtime.
+/// A new profile takes effect once all doors are closed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum DoorTimingProfile {
+    #[default]
+    Standard,
+    Kurz,
+    Lang,
+}
+
+impl DoorTimingProfile {
+    /// All profiles, in the order of their discriminants
+    pub const ALL: [DoorTimingProfile; 3] = [
+        DoorTimingProfile::Standard,
+        DoorTimingProfile::Kurz,
+        DoorTimingProfile::Lang,
+    ];
+
+    pub fn timing(&self) -> DoorTiming {
+        match self {
+            DoorTimingProfile::Standard => DoorTiming {
+                open_dwell: 6.0,
+                warning: 2.0,
+                outside_warning: 30.0,
+                warning_blink_interval: 0.777,
+                outside_warning_blink_interval: 0.393,
+                auto_close: 30.0,
+            },
+            DoorTimingProfile::Kurz => DoorTiming {
+                open_dwell: 4.0,
+                warning: 1.5,
+                outside_warning: 15.0,
+                warning_blink_interval: 0.6,
+                outside_warning_blink_interval: 0.393,
+                auto_close: 20.0,
+            },
+            DoorTimingProfile::Lang => DoorTiming {
+                open_dwell: 10.0,
+                warning: 3.0,
+                outside_warning: 30.0,
+                warning_blink_interval: 0.777,
+                outside_warning_blink_interval: 0.5,
+                auto_close: 60.0,
+            },
+        }
+    }
+}
+
+impl From<i32> for DoorTimingProfile {
+    fn from(value: i32) -> Self {
+        match value {
+            1 => DoorTimingProfile::Kurz,
+            2 => DoorTimingProfile::Lang,
+            _ => DoorTimingProfile::Standard,
+        }
+    }
+}
+
+impl MessageType for DoorTimingProfile {
+    const MESSAGE_META: MessageMeta = MessageMeta::new("gt6n", "door_timing_profile", None);
+}
+
+/// Door warning sound sets of the operators. The initial set is taken from `Cfg_DoorSoundSet`,
+/// a [`DoorSoundProfile`] message switches it at runtime.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum DoorSoundProfile {
+    /// Blink relay clicking with the warning light
+    #[default]
+    Standard,
+    /// Chime when the warning starts
+    Gong,
+    /// Continuous beep until the door is closed
+    Signalton,
+    /// Chime and "Bitte zurückbleiben"
+    Ansage,
+}
+
+impl DoorSoundProfile {
+    pub fn sounds(&self) -> DoorSounds {
+        match self {
+            DoorSoundProfile::Standard => DoorSounds {
+                blink_relais: true,
+                chime: false,
+                beep: false,
+                announcement: false,
+            },
+            DoorSoundProfile::Gong => DoorSounds {
+                blink_relais: true,
+                chime: true,
+                beep: false,
+                announcement: false,
+            },
+            DoorSoundProfile::Signalton => DoorSounds {
+                blink_relais: false,
+                chime: false,
+                beep: true,
+                announcement: false,
+            },
+            DoorSoundProfile::Ansage => DoorSounds {
+                blink_relais: false,
+                chime: true,
+                beep: false,
+                announcement: true,
+            },
+        }
+    }
+}
+
+impl From<i32> for DoorSoundProfile {
+    fn from(value: i32) -> Self {
+        match value {
+            1 => DoorSoundProfile::Gong,
+            2 => DoorSoundProfile::Signalton,
+            3 => DoorSoundProfile::Ansage,
+            _ => DoorSoundProfile::Standard,
+        }
+    }
+}
+
+impl MessageType for DoorSoundProfile {
+    const MESSAGE_META: MessageMeta = MessageMeta::new("gt6n", "door_sound_profile", None);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DoorSounds {
+    /// `Snd_Door_{n}_Warning` with every flash of the warning light
+    pub blink_relais: bool,
+    /// `Snd_Door_{n}_Chime` once when the warning starts
+    pub chime: bool,
+    /// `Snd_Door_{n}_Beep` from the warning until the door is closed
+    pub beep: bool,
+    /// `Snd_Door_{n}_Announcement` once when the warning starts
+    pub announcement: bool,
+}
+
+/// Passengers boarding and alighting at the current stop, sent by the map when the vehicle
+/// stops. The counts are spread over all doors.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PassengerExchange {
+    pub boarding: u32,
+    pub alighting: u32,
+}
+
+impl MessageType for PassengerExchange {
+    const MESSAGE_META: MessageMeta = MessageMeta::new("gt6n", "passenger_exchange", None);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum PlatformSide {
+    Left,
+    Right,
+}
+
+/// Sent by the map when the vehicle has reached a stop position. The stop flag is cleared
+/// once the vehicle departs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct StopPosition {
+    pub platform_side: PlatformSide,
+}
+
+impl MessageType for StopPosition {
+    const MESSAGE_META: MessageMeta = MessageMeta::new("gt6n", "stop_position", None);
+}
+
+/// Speed thresholds of the door release. The release is given below `release_speed` and only
+/// revoked above `revoke_speed`, so it does not flicker at creep speed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DoorReleaseProperties {
+    pub release_speed: f32,
+    pub revoke_speed: f32,
+    /// Automatic release is only given at standstill
+    pub auto_release_speed: f32,
+}
+
+impl DoorReleaseProperties {
+    /// Reads `Cfg_DoorReleaseSpeed`, `Cfg_DoorRevokeSpeed` and `Cfg_DoorAutoReleaseSpeed`,
+    /// unset values fall back to the defaults.
+    pub fn from_config() -> Self {
+        let config = |variable: &str, default: f32| {
+            let value = get_var::<f32>(variable);
+            if value > 0.0 {
+                value
+            } else {
+                default
+            }
+        };
+
+        let release_speed = config("Cfg_DoorReleaseSpeed", 1.0);
+
+        Self {
+            release_speed,
+            revoke_speed: config("Cfg_DoorRevokeSpeed", 1.5).max(release_speed),
+            auto_release_speed: config("Cfg_DoorAutoReleaseSpeed", 0.1).min(release_speed),
+        }
+    }
+}
+
+/// Automatic door release, given once per stop. It is latched when the vehicle comes to
+/// rest at a stop and ends with Zwangsschließen, the door switch moved to closed or when
+/// leaving the stop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AutoRelease {
+    pub released: bool,
+    prev_condition: bool,
+    prev_at_stop: bool,
+    prev_switch_closed: bool,
+}
+
+impl Default for AutoRelease {
+    fn default() -> Self {
+        // Der Türschalter steht anfangs auf geschlossen
+        Self {
+            released: false,
+            prev_condition: false,
+            prev_at_stop: false,
+            prev_switch_closed: true,
+        }
+    }
+}
+
+impl AutoRelease {
+    /// `condition` is the automatic mode at a stop below the auto release speed
+    pub fn update(
+        &mut self,
+        condition: bool,
+        at_stop: bool,
+        force_close: bool,
+        switch_closed: bool,
+    ) -> bool {
+        if condition && !self.prev_condition {
+            self.released = true;
+        }
+
+        if force_close
+            || (switch_closed && !self.prev_switch_closed)
+            || (!at_stop && self.prev_at_stop)
+        {
+            self.released = false;
+        }
+
+        self.prev_condition = condition;
+        self.prev_at_stop = at_stop;
+        self.prev_switch_closed = switch_closed;
+
+        self.released
+    }
+}
+
+/// Depot action resetting the door counters after maintenance
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct DoorMaintenance;
+
+impl MessageType for DoorMaintenance {
+    const MESSAGE_META: MessageMeta = MessageMeta::new("gt6n", "door_maintenance", None);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DoorTiming {
+    /// Time the door stays open after the last request
+    pub open_dwell: f32,
+    /// Duration of the warning before the door starts closing
+    pub warning: f32,
+    /// Time the outside warning keeps running after all doors have closed
+    pub outside_warning: f32,
+    pub warning_blink_interval: f32,
+    pub outside_warning_blink_interval: f32,
+    /// Doors held open longer than this are closed forcibly
+    pub auto_close: f32,
+}
+
+/// Operating time of a door. Whole hours are carried out of the seconds, so adding a tick
+/// stays exact however long the door has been in service.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct OperatingTime {
+    pub hours: u32,
+    /// Seconds within the current hour
+    pub seconds: f32,
+}
+
+impl OperatingTime {
+    pub fn add(&mut self, delta: f32) {
+        self.seconds += delta;
+
+        if self.seconds >= 3600.0 {
+            let hours = (self.seconds / 3600.0).floor();
+            self.hours += hours as u32;
+            self.seconds -= hours * 3600.0;
+        }
+    }
+}
+
+/// Wear of the door drive from its cycles, 0.0 is new and 1.0 fully worn
+pub fn door_wear(cycles: u32) -> f32 {
+    (cycles as f32 / WEAR_CYCLES).min(1.0)
+}
+
+/// Phase of a door, derived from the outputs of the door control
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DoorPhase {
+    #[default]
+    Closed,
+    Open,
+    Warning,
+    Closing,
+}
+
+impl DoorPhase {
+    pub fn from_control(
+        target: ElectricSlidingPlugDoorPairTarget,
+        warning: bool,
+        closed: bool,
+    ) -> Self {
+        match target {
+            ElectricSlidingPlugDoorPairTarget::Open if warning => DoorPhase::Warning,
+            ElectricSlidingPlugDoorPairTarget::Open => DoorPhase::Open,
+            _ if closed => DoorPhase::Closed,
+            ElectricSlidingPlugDoorPairTarget::Close => DoorPhase::Closing,
+            // Eine stromlose Tür kann von Hand offen gehalten werden
+            ElectricSlidingPlugDoorPairTarget::NoEnergy => DoorPhase::Open,
+        }
+    }
+}
+
+/// Forced closing of a single door, by Zwangsschließen or after the door was held open
+/// longer than `auto_close`. Doors under an explicit open command are not closed by time.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ForcedClosing {
+    pub forced: bool,
+    open_timer: f32,
+    prev_force_close: bool,
+}
+
+impl ForcedClosing {
+    pub fn update(
+        &mut self,
+        phase: DoorPhase,
+        request: bool,
+        explicit_open: bool,
+        force_close: bool,
+        auto_close: f32,
+        delta: f32,
+    ) -> bool {
+        if phase == DoorPhase::Closed || explicit_open {
+            self.open_timer = 0.0;
+        } else {
+            self.open_timer += delta;
+        }
+
+        // Lichtschranke und Taster unterbrechen das Zwangsschließen nicht. Erst an der
+        // geschlossenen Tür und ohne anstehende Anforderung ist es beendet.
+        if !request && phase == DoorPhase::Closed {
+            self.forced = false;
+        }
+
+        if (force_close && !self.prev_force_close) || self.open_timer >= auto_close {
+            self.forced = self.forced || phase != DoorPhase::Closed;
+        }
+        self.prev_force_close = force_close;
+
+        self.forced
+    }
+}
+
+/// Mode handed to the door control. The driver override wins over the forced closing,
+/// a broken light barrier interrupts it.
+pub fn door_control_mode(
+    driver_override: DoorControlMode,
+    hold_open: bool,
+    emergency_release: bool,
+    closed: bool,
+    forced: bool,
+    obstructed: bool,
+) -> DoorControlMode {
+    if hold_open && !closed {
+        DoorControlMode::Open
+    } else if emergency_release {
+        // Nach dem Quittieren der Notentriegelung wird regulär gewarnt und geschlossen
+        if closed {
+            driver_override
+        } else {
+            DoorControlMode::Open
+        }
+    } else {
+        match driver_override {
+            DoorControlMode::Automatic if forced && !obstructed => DoorControlMode::Close,
+            mode => mode,
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct DoorsState {
     pub doors_with_controller: Vec<DoorsWithController>,
     pub released: Shared<bool>,
     pub requests: Vec<Shared<bool>>,
     pub vehicle_speed: Shared<f32>,
-    pub door_1_override: Shared<DoorControlMode>,
+    pub door_overrides: Vec<Shared<DoorControlMode>>,
     pub override_no_warning: Shared<bool>,
     pub all_closed: Shared<bool>,
+    pub emergency_released: Shared<bool>,
+    /// Zwangsschließen per door, a rising edge starts the forced closing
+    pub force_close: Vec<Shared<bool>>,
+    /// Set while the door switch commands all doors open
+    pub open_all: Shared<bool>,
+    pub timing_profile: Shared<DoorTimingProfile>,
+    pub sound_profile: Shared<DoorSoundProfile>,
+    pub passengers_on_board: Shared<u32>,
+    pub maintenance_due: Shared<bool>,
+    /// Haltewunsch, set by the outside door buttons while the doors are not released
+    pub stop_request: Shared<bool>,
+    /// Set by a [`StopPosition`] on the door side
+    pub at_stop: Shared<bool>,
+    pub release_properties: DoorReleaseProperties,
 }
 
 #[derive(Clone, Debug)]
 pub struct DoorsWithController {
     pub door: ElectricSlidingPlugDoorPairState,
-    pub control: DoorControlState,
+    pub phase: Shared<DoorPhase>,
+    pub warning: Shared<bool>,
+    /// Mode handed to the door control
+    pub mode: Shared<DoorControlMode>,
     pub closed: Shared<bool>,
+    /// Notentriegelung: the door drive is de-energised so the leaves can be pushed by hand.
+    pub emergency_release: Shared<bool>,
+    /// Broken while a passenger passes the door
+    pub light_barrier: Shared<bool>,
+    pub passengers_boarding: Shared<u32>,
+    pub passengers_alighting: Shared<u32>,
+    /// Keeps the door open, e.g. while the ramp is deployed
+    pub hold_open: Shared<bool>,
+    /// The drive is moving the leaves, i.e. the door is opening or closing
+    pub moving: Shared<bool>,
+    /// Open/close cycles, kept in `Door_{n}_Cycles`. The counters are not persisted across
+    /// sessions, they start from the values of their vars at load.
+    pub cycles: Shared<u32>,
+    /// Time the door was not closed, kept in `Door_{n}_OperatingHours` and
+    /// `Door_{n}_OperatingSeconds`
+    pub operating_time: Shared<OperatingTime>,
+    /// Follows the cycles and is written to `Door_{n}_Wear`. The drive takes its friction and
+    /// speeds from the wear when the vehicle is loaded.
+    pub wear: Shared<f32>,
+    pub maintenance_due: Shared<bool>,
 }
 
-pub fn doors() -> DoorsState {
+pub fn doors(blink_clock: BlinkClockState) -> DoorsState {
     log::info!("Adding doors ------------------------------");
 
     let system_active = Shared::new(true);
 
     let released = Shared::new(false);
-    let door_1_force = Shared::new(DoorControlMode::default());
+    let override_no_warning = Shared::new(false);
+
+    let initial_profile = DoorTimingProfile::from(get_var::<i32>("Cfg_DoorTimingProfile"));
+    log::info!("Door timing profile: {:?}", initial_profile);
+
+    let timing_profile = Shared::new(initial_profile);
+    let active_profile = Shared::new(initial_profile);
+    let timing = active_profile.process(|profile| profile.timing(), initial_profile.timing());
+
+    let initial_sound_profile = DoorSoundProfile::from(get_var::<i32>("Cfg_DoorSoundSet"));
+    log::info!("Door sound profile: {:?}", initial_sound_profile);
+
+    let sound_profile = Shared::new(initial_sound_profile);
+    let sounds = sound_profile.process(|profile| profile.sounds(), initial_sound_profile.sounds());
 
     let requests: Vec<_> = std::iter::repeat_with(|| Shared::new(false))
         .take(4)
         .collect();
 
-    let door_with_controller =
-        |door_number: usize,
-         start_speed: f32,
-         close_start_speed: f32,
-         reflection_open: f32,
-         reflection_close: f32,
-         door_1_force: Option<Shared<DoorControlMode>>| {
-            let door_target = Shared::new(ElectricSlidingPlugDoorPairTarget::NoEnergy);
-
-            let door_prop = ElectricSlidingPlugDoorPairProperties::builder()
-                .plug_radius(PLUG_RADIUS)
-                .shift_distance(SHIFT_DISTANCE)
-                .friction(FRICTION)
-                .open_start_speed(start_speed)
-                .open_end_speed(OPEN_END_SPEED)
-                .open_start_end_change_position(OPEN_START_END_CHANGE_POSITION)
-                .close_start_speed(close_start_speed)
-                .close_end_speed(CLOSE_END_SPEED)
-                .close_start_end_change_position(CLOSE_START_END_CHANGE_POSITION)
-                .traction_stiftness(TRACTION_STIFTNESS)
-                .reflection_open(reflection_open)
-                .reflection_close(reflection_close)
-                .sound_open_start(format!("Snd_Door_{}_Open_Start", door_number + 1))
-                .sound_open_end(format!("Snd_Door_{}_Open_End", door_number + 1))
-                .sound_close_start(format!("Snd_Door_{}_Close_Start", door_number + 1))
-                .sound_close_transition(format!("Snd_Door_{}_Close_Trans", door_number + 1))
-                .sound_close_end(format!("Snd_Door_{}_Close_End", door_number + 1))
-                .variable_x_rail(format!("Door_{}_R", door_number + 1))
-                .variable_y_blade_a(format!("Door_{}_1", door_number + 1))
-                .variable_y_blade_b(format!("Door_{}_2", door_number + 1))
-                .build();
-
-            let door = door_target.electric_sliding_plug_door_pair(door_prop);
-
-            let control_properties = DoorControlProperties::builder()
-                .request_time(6.0)
-                .warning_time(2.0)
-                .set_system_active(system_active.clone())
-                .set_request(requests[door_number].clone())
-                .set_released(released.clone())
-                .set_door_closed(door.position.clone());
-
-            let control_properties = if let Some(force) = door_1_force {
-                control_properties.set_force(force.clone()).build()
-            } else {
-                control_properties.build()
-            };
+    let force_close: Vec<_> = std::iter::repeat_with(|| Shared::new(false))
+        .take(4)
+        .collect();
 
-            let control = door_control(control_properties);
+    let door_overrides: Vec<_> = std::iter::repeat_with(|| Shared::new(DoorControlMode::default()))
+        .take(4)
+        .collect();
 
-            control.door_target.forward(&door_target);
+    let door_with_controller = |door_number: usize,
+                                start_speed: f32,
+                                close_start_speed: f32,
+                                reflection_open: f32,
+                                reflection_close: f32| {
+        let emergency_release = Shared::new(false);
+        let light_barrier = Shared::new(false);
+        let hold_open = Shared::new(false);
+        let mode = Shared::new(DoorControlMode::default());
 
-            let closed = door.position.process(
-                |v| *v == ElectricSlidingPlugDoorPairPositionState::FullyClosed,
-                false,
-            );
+        let cycles = Shared::new(
+            get_var::<i32>(format!("Door_{}_Cycles", door_number + 1).as_str()).max(0) as u32,
+        );
+        let operating_time = Shared::new(OperatingTime {
+            hours: get_var::<i32>(format!("Door_{}_OperatingHours", door_number + 1).as_str())
+                .max(0) as u32,
+            seconds: get_var::<f32>(format!("Door_{}_OperatingSeconds", door_number + 1).as_str())
+                .max(0.0),
+        });
 
-            DoorsWithController {
-                door,
-                control,
-                closed,
-            }
-        };
+        // Verschleiß: die Tür wird schwergängiger und langsamer
+        let wear = door_wear(cycles.get());
+
+        let door_prop = ElectricSlidingPlugDoorPairProperties::builder()
+            .plug_radius(PLUG_RADIUS)
+            .shift_distance(SHIFT_DISTANCE)
+            .friction(FRICTION * (1.0 + wear))
+            .open_start_speed(start_speed * (1.0 - 0.2 * wear))
+            .open_end_speed(OPEN_END_SPEED)
+            .open_start_end_change_position(OPEN_START_END_CHANGE_POSITION)
+            .close_start_speed(close_start_speed * (1.0 - 0.2 * wear))
+            .close_end_speed(CLOSE_END_SPEED)
+            .close_start_end_change_position(CLOSE_START_END_CHANGE_POSITION)
+            .traction_stiftness(TRACTION_STIFTNESS)
+            .reflection_open(reflection_open)
+            .reflection_close(reflection_close)
+            .sound_open_start(format!("Snd_Door_{}_Open_Start", door_number + 1))
+            .sound_open_end(format!("Snd_Door_{}_Open_End", door_number + 1))
+            .sound_close_start(format!("Snd_Door_{}_Close_Start", door_number + 1))
+            .sound_close_transition(format!("Snd_Door_{}_Close_Trans", door_number + 1))
+            .sound_close_end(format!("Snd_Door_{}_Close_End", door_number + 1))
+            .variable_x_rail(format!("Door_{}_R", door_number + 1))
+            .variable_y_blade_a(format!("Door_{}_1", door_number + 1))
+            .variable_y_blade_b(format!("Door_{}_2", door_number + 1))
+            .build();
+
+        let door_target = Shared::new(ElectricSlidingPlugDoorPairTarget::NoEnergy);
+        let door = door_target.electric_sliding_plug_door_pair(door_prop);
+
+        let closed = door.position.process(
+            |v| *v == ElectricSlidingPlugDoorPairPositionState::FullyClosed,
+            false,
+        );
+
+        // Die Lichtschranke hält die Tür offen und startet die Offenhaltezeit neu
+        let request = requests[door_number].or(&light_barrier);
+
+        // Die Türsteuerung hat feste Zeiten, daher läuft für jedes Zeitprofil eine eigene
+        let controls: Vec<_> = DoorTimingProfile::ALL
+            .iter()
+            .map(|profile| {
+                let timing = profile.timing();
+
+                door_control(
+                    DoorControlProperties::builder()
+                        .request_time(timing.open_dwell)
+                        .warning_time(timing.warning)
+                        .set_system_active(system_active.clone())
+                        .set_request(request.clone())
+                        .set_released(released.clone())
+                        .set_door_closed(door.position.clone())
+                        .set_force(mode.clone())
+                        .build(),
+                )
+            })
+            .collect();
+
+        let control_target = by_profile(
+            controls.iter().map(|c| c.door_target.clone()).collect(),
+            active_profile.clone(),
+        );
+        let warning = by_profile(
+            controls.iter().map(|c| c.warning.clone()).collect(),
+            active_profile.clone(),
+        );
+
+        let phase = Shared::new(DoorPhase::Closed);
+
+        {
+            let door_target = door_target.clone();
+            let phase = phase.clone();
+            let warning = warning.clone();
+            let closed = closed.clone();
+            let emergency_release = emergency_release.clone();
+
+            spawn(async move {
+                loop {
+                    let target = control_target.get();
+
+                    // Die Notentriegelung schaltet den Türantrieb stromlos
+                    door_target.set_only_on_change(if emergency_release.get() {
+                        ElectricSlidingPlugDoorPairTarget::NoEnergy
+                    } else {
+                        target
+                    });
+
+                    phase.set_only_on_change(DoorPhase::from_control(
+                        target,
+                        warning.get(),
+                        closed.get(),
+                    ));
+
+                    wait::next_tick().await;
+                }
+            });
+        }
+
+        DoorsWithController {
+            door,
+            phase,
+            warning,
+            mode,
+            closed,
+            emergency_release,
+            light_barrier,
+            passengers_boarding: Shared::new(0),
+            passengers_alighting: Shared::new(0),
+            hold_open,
+            moving: Shared::new(false),
+            cycles,
+            operating_time,
+            wear: Shared::new(wear),
+            maintenance_due: Shared::new(false),
+        }
+    };
 
     let doors_with_controller = vec![
-        door_with_controller(0, 0.6, 0.5, 0.03, 0.05, Some(door_1_force.clone())),
-        door_with_controller(1, 0.65, 0.45, 0.05, 0.05, None),
-        door_with_controller(2, 0.62, 0.42, 0.05, 0.05, None),
-        door_with_controller(3, 0.58, 0.48, 0.03, 0.05, None),
+        door_with_controller(0, 0.6, 0.5, 0.03, 0.05),
+        door_with_controller(1, 0.65, 0.45, 0.05, 0.05),
+        door_with_controller(2, 0.62, 0.42, 0.05, 0.05),
+        door_with_controller(3, 0.58, 0.48, 0.03, 0.05),
     ];
 
     let state = DoorsState {
@@ -130,8 +672,8 @@ pub fn doors() -> DoorsState {
         vehicle_speed: Shared::new(0.0),
         released,
         requests,
-        door_1_override: door_1_force,
-        override_no_warning: Shared::new(false),
+        door_overrides,
+        override_no_warning,
         all_closed: Shared::<bool>::and_vec(
             doors_with_controller
                 .clone()
@@ -139,79 +681,129 @@ pub fn doors() -> DoorsState {
                 .map(|v| v.closed.clone())
                 .collect(),
         ),
+        emergency_released: Shared::new(false),
+        force_close,
+        open_all: Shared::new(false),
+        timing_profile,
+        sound_profile,
+        passengers_on_board: Shared::new(0),
+        maintenance_due: doors_with_controller.iter().skip(1).fold(
+            doors_with_controller[0].maintenance_due.clone(),
+            |due, door| due.or(&door.maintenance_due),
+        ),
+        stop_request: Shared::new(false),
+        at_stop: Shared::new(false),
+        release_properties: DoorReleaseProperties::from_config(),
     };
 
-    state
-        .door_1_override
-        .process(|&v| v == DoorControlMode::Automatic, true)
-        .and(&state.doors_with_controller[0].control.warning)
-        .blink_relais_with_light_and_sound(BlinkRelaisWithLightAndSoundProperties {
-            blink_relais_properties: BlinkRelaisProperties {
-                interval: 0.777,
-                on_time: 0.388,
-                reset_time: None,
-            },
-            light_and_sound: LightAndSoundVarPair {
-                light: "Door_1_WarnlightI".to_string(),
-                sound: "Snd_Door_1_Warning".to_string(),
-            },
+    {
+        let timing_profile = state.timing_profile.clone();
+        let all_closed = state.all_closed.clone();
+        let active_profile = active_profile.clone();
+
+        // Ein neues Zeitprofil gilt erst, wenn alle Türen geschlossen sind
+        spawn(async move {
+            loop {
+                if all_closed.get() {
+                    active_profile.set_only_on_change(timing_profile.get());
+                }
+
+                wait::next_tick().await;
+            }
         });
+    }
 
-    blink_relais_multiple_entries(BlinkRelaisMultipleEntriesProperties {
-        interval: 0.777,
-        on_time: 0.388,
-        reset_time: None,
-        entries: vec![
-            (
-                state.doors_with_controller[1].control.warning.clone(),
-                LightAndSoundVarPair {
-                    light: "Door_2_WarnlightI".to_string(),
-                    sound: "Snd_Door_2_Warning".to_string(),
-                },
-            ),
-            (
-                state.doors_with_controller[2].control.warning.clone(),
-                LightAndSoundVarPair {
-                    light: "Door_3_WarnlightI".to_string(),
-                    sound: "Snd_Door_3_Warning".to_string(),
-                },
-            ),
-            (
-                state.doors_with_controller[3].control.warning.clone(),
-                LightAndSoundVarPair {
-                    light: "Door_4_WarnlightI".to_string(),
-                    sound: "Snd_Door_4_Warning".to_string(),
-                },
-            ),
-        ],
-    });
+    for (i, door) in state.doors_with_controller.iter().enumerate() {
+        door_mode(
+            door.clone(),
+            state.requests[i].clone(),
+            state.door_overrides[i].clone(),
+            state.open_all.clone(),
+            state.force_close[i].clone(),
+            timing.clone(),
+        );
+        passenger_flow(door.clone(), state.passengers_on_board.clone());
+        door_counters(i, door.clone());
+        door_motion(i, door.clone());
+    }
 
-    let all_doors_closed = Shared::<bool>::and_vec(
-        state
-            .doors_with_controller
-            .iter()
-            .map(|v| v.closed.clone())
-            .collect(),
+    let warning_blink_interval = timing.process(
+        |timing| timing.warning_blink_interval,
+        initial_profile.timing().warning_blink_interval,
+    );
+
+    // Bei übersteuerter Tür wird innen nicht gewarnt
+    let warnings: Vec<_> = state
+        .doors_with_controller
+        .iter()
+        .zip(state.door_overrides.iter())
+        .map(|(door, force)| {
+            force
+                .process(|&v| v == DoorControlMode::Automatic, true)
+                .and(&door.warning)
+        })
+        .collect();
+
+    let blink_relais_sound = sounds.process(
+        |sounds| sounds.blink_relais,
+        initial_sound_profile.sounds().blink_relais,
     );
 
-    let warning_outside_relay = door_warning_outside_relay_with_stop_on_speed(
-        DoorWarningOutsideRelayWithStopOnSpeedProperties::builder()
-            .timer_after_closed(30.0)
-            .max_speed(3.0 / 3.6)
-            .released(state.released.clone())
-            .all_doors_closed(all_doors_closed.clone())
-            .speed(state.vehicle_speed.clone())
-            .build(),
+    for (i, door) in state.doors_with_controller.iter().enumerate() {
+        door_warning_sounds(i, door.clone(), sounds.clone());
+    }
+
+    let door_1_blink = blink_clock.blinker(warnings[0].clone(), warning_blink_interval.clone());
+
+    door_1_blink.to_float().var_writer("Door_1_WarnlightI");
+    door_1_blink
+        .and(&blink_relais_sound)
+        .trigger_sound("Snd_Door_1_Warning");
+
+    // Die Türen 2 bis 4 teilen sich ein Blinkrelais
+    let doors_234_blink = blink_clock.blinker(
+        warnings[1].or(&warnings[2]).or(&warnings[3]),
+        warning_blink_interval,
+    );
+
+    for (i, warning) in warnings.iter().enumerate().skip(1) {
+        let blink = doors_234_blink.and(warning);
+
+        blink
+            .to_float()
+            .var_writer(format!("Door_{}_WarnlightI", i + 1));
+        blink
+            .and(&blink_relais_sound)
+            .trigger_sound(format!("Snd_Door_{}_Warning", i + 1).as_str());
+    }
+
+    let warning_outside_relay = by_profile(
+        DoorTimingProfile::ALL
+            .iter()
+            .map(|profile| {
+                door_warning_outside_relay_with_stop_on_speed(
+                    DoorWarningOutsideRelayWithStopOnSpeedProperties::builder()
+                        .timer_after_closed(profile.timing().outside_warning)
+                        .max_speed(OUTSIDE_WARNING_MAX_SPEED)
+                        .released(state.released.clone())
+                        .all_doors_closed(state.all_closed.clone())
+                        .speed(state.vehicle_speed.clone())
+                        .build(),
+                )
+            })
+            .collect(),
+        active_profile,
     );
 
     warning_outside_relay.var_writer("Snd_Relais_Doorwarn");
 
-    let outside_warning_blinker_relais =
-        warning_outside_relay.blink_relais(BlinkRelaisProperties {
-            interval: 0.393,
-            on_time: 0.196,
-            reset_time: None,
-        });
+    let outside_warning_blinker_relais = blink_clock.blinker(
+        warning_outside_relay,
+        timing.process(
+            |timing| timing.outside_warning_blink_interval,
+            initial_profile.timing().outside_warning_blink_interval,
+        ),
+    );
 
     outside_warning_blinker_relais
         .to_float()
@@ -221,6 +813,250 @@ pub fn doors() -> DoorsState {
     state
 }
 
+/// Follows the value belonging to the active timing profile, `values` are ordered like
+/// [`DoorTimingProfile::ALL`]
+fn by_profile<T: Clone + PartialEq + 'static>(
+    values: Vec<Shared<T>>,
+    profile: Shared<DoorTimingProfile>,
+) -> Shared<T> {
+    let selected = Shared::new(values[profile.get() as usize].get());
+    let s = selected.clone();
+
+    spawn(async move {
+        loop {
+            s.set_only_on_change(values[profile.get() as usize].get());
+
+            wait::next_tick().await;
+        }
+    });
+
+    selected
+}
+
+/// Feeds the driver override, Zwangsschließen, the timed auto-close and the interlocks
+/// into the mode of the door control
+fn door_mode(
+    door: DoorsWithController,
+    request: Shared<bool>,
+    driver_override: Shared<DoorControlMode>,
+    open_all: Shared<bool>,
+    force_close: Shared<bool>,
+    timing: Shared<DoorTiming>,
+) {
+    spawn(async move {
+        let mut forced_closing = ForcedClosing::default();
+
+        loop {
+            let closed = door.closed.get();
+
+            let driver_override = driver_override.get();
+
+            let forced = forced_closing.update(
+                door.phase.get(),
+                request.get(),
+                open_all.get() || driver_override == DoorControlMode::Open,
+                force_close.get(),
+                timing.get().auto_close,
+                delta(),
+            );
+
+            door.mode.set_only_on_change(door_control_mode(
+                driver_override,
+                door.hold_open.get(),
+                door.emergency_release.get(),
+                closed,
+                forced,
+                door.light_barrier.get(),
+            ));
+
+            wait::next_tick().await;
+        }
+    });
+}
+
+impl DoorsState {
+    pub fn stop_position(&self, stop: StopPosition) {
+        self.at_stop.set(stop.platform_side == DOOR_SIDE);
+    }
+
+    pub fn reset_maintenance(&self) {
+        for door in self.doors_with_controller.iter() {
+            door.cycles.set(0);
+            door.operating_time.set(OperatingTime::default());
+        }
+    }
+
+    /// Spreads the passengers of a stop over the doors. Passengers can only alight if they are
+    /// on board.
+    pub fn add_passenger_exchange(&self, exchange: PassengerExchange) {
+        let doors = self.doors_with_controller.len() as u32;
+        let alighting = exchange.alighting.min(self.passengers_on_board.get());
+
+        for (i, door) in self.doors_with_controller.iter().enumerate() {
+            let share = |count: u32| count / doors + u32::from((i as u32) < count % doors);
+
+            door.passengers_boarding
+                .set(door.passengers_boarding.get() + share(exchange.boarding));
+            door.passengers_alighting
+                .set(door.passengers_alighting.get() + share(alighting));
+        }
+    }
+}
+
+/// Detects the movement of the leaves from the variables the door drive writes. A door pushed
+/// by hand after the Notentriegelung does not count as moving.
+fn door_motion(door_number: usize, door: DoorsWithController) {
+    let position_vars = [
+        format!("Door_{}_R", door_number + 1),
+        format!("Door_{}_1", door_number + 1),
+    ];
+
+    spawn(async move {
+        let mut prev_position = [0.0; 2];
+
+        loop {
+            let position = position_vars
+                .each_ref()
+                .map(|var| get_var::<f32>(var.as_str()));
+            let moved = position
+                .iter()
+                .zip(prev_position.iter())
+                .any(|(position, prev)| (position - prev).abs() > f32::EPSILON);
+            prev_position = position;
+
+            door.moving
+                .set_only_on_change(moved && !door.emergency_release.get());
+
+            wait::next_tick().await;
+        }
+    });
+}
+
+fn door_counters(door_number: usize, door: DoorsWithController) {
+    let cycles_var = format!("Door_{}_Cycles", door_number + 1);
+    let operating_hours_var = format!("Door_{}_OperatingHours", door_number + 1);
+    let operating_seconds_var = format!("Door_{}_OperatingSeconds", door_number + 1);
+    let wear_var = format!("Door_{}_Wear", door_number + 1);
+
+    spawn(async move {
+        let mut prev_phase = door.phase.get();
+
+        loop {
+            let phase = door.phase.get();
+
+            if prev_phase == DoorPhase::Closed && phase == DoorPhase::Open {
+                door.cycles.set(door.cycles.get() + 1);
+            }
+            prev_phase = phase;
+
+            let mut operating_time = door.operating_time.get();
+            if phase != DoorPhase::Closed {
+                operating_time.add(delta());
+                door.operating_time.set(operating_time);
+            }
+
+            door.wear.set_only_on_change(door_wear(door.cycles.get()));
+
+            set_var(&cycles_var, &(door.cycles.get() as i32));
+            set_var(&operating_hours_var, &(operating_time.hours as i32));
+            set_var(&operating_seconds_var, &operating_time.seconds);
+            set_var(&wear_var, &door.wear.get());
+
+            let due = door.cycles.get() >= MAINTENANCE_CYCLES
+                || operating_time.hours >= MAINTENANCE_OPERATING_HOURS;
+
+            if due && !door.maintenance_due.get() {
+                log::info!("Tür {}: Wartung fällig", door_number + 1);
+            }
+            door.maintenance_due.set_only_on_change(due);
+
+            wait::next_tick().await;
+        }
+    });
+}
+
+/// Chime, beep and announcement follow the door phases instead of the blink relay
+fn door_warning_sounds(door_number: usize, door: DoorsWithController, sounds: Shared<DoorSounds>) {
+    let chime = format!("Snd_Door_{}_Chime", door_number + 1);
+    let beep = format!("Snd_Door_{}_Beep", door_number + 1);
+    let announcement = format!("Snd_Door_{}_Announcement", door_number + 1);
+
+    spawn(async move {
+        let mut prev_phase = door.phase.get();
+
+        loop {
+            let sounds = sounds.get();
+            let phase = door.phase.get();
+
+            if phase == DoorPhase::Warning && prev_phase != DoorPhase::Warning {
+                if sounds.chime {
+                    set_var(&chime, &true);
+                }
+                if sounds.announcement {
+                    set_var(&announcement, &true);
+                }
+            }
+            prev_phase = phase;
+
+            set_var(
+                &beep,
+                &(sounds.beep
+                    && (phase == DoorPhase::Warning
+                        || (phase == DoorPhase::Closing && !door.closed.get()))),
+            );
+
+            wait::next_tick().await;
+        }
+    });
+}
+
+/// Lets the waiting passengers pass an open door one after another. Each passage breaks the
+/// light barrier, which keeps the door open.
+fn passenger_flow(door: DoorsWithController, passengers_on_board: Shared<u32>) {
+    spawn(async move {
+        let mut timer = 0.0;
+        let mut boarding = false;
+
+        loop {
+            let door_open = door.phase.get() == DoorPhase::Open && !door.closed.get();
+
+            if timer > 0.0 {
+                timer -= delta();
+
+                if timer <= PASSENGER_GAP_TIME && door.light_barrier.get() {
+                    door.light_barrier.set(false);
+
+                    let on_board = passengers_on_board.get();
+                    passengers_on_board.set(if boarding {
+                        on_board + 1
+                    } else {
+                        on_board.saturating_sub(1)
+                    });
+                }
+            } else if door_open {
+                let alighting = door.passengers_alighting.get();
+                let waiting = door.passengers_boarding.get();
+
+                // Erst aussteigen lassen, dann einsteigen
+                if alighting > 0 {
+                    door.passengers_alighting.set(alighting - 1);
+                    boarding = false;
+                } else if waiting > 0 {
+                    door.passengers_boarding.set(waiting - 1);
+                    boarding = true;
+                }
+
+                if alighting > 0 || waiting > 0 {
+                    timer = PASSENGER_PASSAGE_TIME + PASSENGER_GAP_TIME;
+                    door.light_barrier.set(true);
+                }
+            }
+
+            wait::next_tick().await;
+        }
+    });
+}
+
 #[derive(Clone, Debug)]
 pub enum DoorSwitchState {
     Closed,
@@ -228,3 +1064,188 @@ pub enum DoorSwitchState {
     Open,
     DoorOne,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auto_release_is_given_once_per_stop() {
+        let mut auto_release = AutoRelease::default();
+
+        assert!(auto_release.update(true, true, false, true));
+        assert!(auto_release.update(true, true, false, true));
+
+        // Zwangsschließen beendet die Freigabe, auch wenn das Fahrzeug weiter steht
+        assert!(!auto_release.update(true, true, true, true));
+        assert!(!auto_release.update(true, true, false, true));
+
+        // Erst an der nächsten Haltestelle wird wieder freigegeben
+        assert!(!auto_release.update(false, false, false, true));
+        assert!(auto_release.update(true, true, false, true));
+    }
+
+    #[test]
+    fn auto_release_ends_when_switch_is_moved_to_closed() {
+        let mut auto_release = AutoRelease::default();
+
+        assert!(auto_release.update(true, true, false, false));
+        assert!(!auto_release.update(true, true, false, true));
+        assert!(!auto_release.update(true, true, false, false));
+    }
+
+    #[test]
+    fn auto_release_ends_when_leaving_the_stop() {
+        let mut auto_release = AutoRelease::default();
+
+        assert!(auto_release.update(true, true, false, true));
+        assert!(!auto_release.update(false, false, false, true));
+    }
+
+    #[test]
+    fn operating_time_keeps_counting_after_long_service() {
+        let mut operating_time = OperatingTime {
+            hours: 400,
+            seconds: 3599.99,
+        };
+
+        operating_time.add(0.02);
+        assert_eq!(operating_time.hours, 401);
+        assert!((operating_time.seconds - 0.01).abs() < 1e-3);
+
+        // 50 Stunden in Ticks von 1/64 s
+        for _ in 0..50 * 3600 * 64 {
+            operating_time.add(1.0 / 64.0);
+        }
+        assert_eq!(operating_time.hours, 451);
+    }
+
+    #[test]
+    fn operating_time_carries_several_hours_at_once() {
+        let mut operating_time = OperatingTime::default();
+
+        operating_time.add(3.0 * 3600.0 + 5.0);
+        assert_eq!(operating_time.hours, 3);
+        assert!((operating_time.seconds - 5.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn wear_follows_cycles() {
+        assert_eq!(door_wear(0), 0.0);
+        assert_eq!(door_wear(20_000), 0.5);
+        assert_eq!(door_wear(100_000), 1.0);
+    }
+
+    #[test]
+    fn phase_follows_door_control() {
+        use ElectricSlidingPlugDoorPairTarget as Target;
+
+        assert_eq!(
+            DoorPhase::from_control(Target::Open, false, false),
+            DoorPhase::Open
+        );
+        assert_eq!(
+            DoorPhase::from_control(Target::Open, true, false),
+            DoorPhase::Warning
+        );
+        assert_eq!(
+            DoorPhase::from_control(Target::Close, true, false),
+            DoorPhase::Closing
+        );
+        assert_eq!(
+            DoorPhase::from_control(Target::Close, false, true),
+            DoorPhase::Closed
+        );
+        assert_eq!(
+            DoorPhase::from_control(Target::NoEnergy, false, false),
+            DoorPhase::Open
+        );
+        assert_eq!(
+            DoorPhase::from_control(Target::NoEnergy, false, true),
+            DoorPhase::Closed
+        );
+    }
+
+    #[test]
+    fn forced_closing_overrides_automatic_unless_obstructed() {
+        use DoorControlMode::*;
+
+        assert_eq!(
+            door_control_mode(Automatic, false, false, false, true, false),
+            Close
+        );
+        assert_eq!(
+            door_control_mode(Automatic, false, false, false, true, true),
+            Automatic
+        );
+        assert_eq!(
+            door_control_mode(Open, false, false, false, true, false),
+            Open
+        );
+        assert_eq!(
+            door_control_mode(Close, true, false, false, false, false),
+            Open
+        );
+        assert_eq!(
+            door_control_mode(Close, true, false, true, false, false),
+            Close
+        );
+    }
+
+    #[test]
+    fn emergency_release_keeps_open_door_open() {
+        use DoorControlMode::*;
+
+        assert_eq!(
+            door_control_mode(Close, false, true, false, true, false),
+            Open
+        );
+        assert_eq!(
+            door_control_mode(Automatic, false, true, true, true, false),
+            Automatic
+        );
+    }
+
+    #[test]
+    fn door_held_open_is_forced_closed_after_auto_close() {
+        let mut forced_closing = ForcedClosing::default();
+
+        assert!(!forced_closing.update(DoorPhase::Open, true, false, false, 10.0, 6.0));
+        assert!(forced_closing.update(DoorPhase::Open, true, false, false, 10.0, 6.0));
+        assert!(forced_closing.update(DoorPhase::Closed, true, false, false, 10.0, 0.1));
+        assert!(!forced_closing.update(DoorPhase::Closed, false, false, false, 10.0, 0.1));
+    }
+
+    #[test]
+    fn zwangsschliessen_acts_on_rising_edge() {
+        let mut forced_closing = ForcedClosing::default();
+
+        assert!(forced_closing.update(DoorPhase::Open, false, false, true, 60.0, 0.1));
+        assert!(!forced_closing.update(DoorPhase::Closed, false, false, true, 60.0, 0.1));
+        assert!(!forced_closing.update(DoorPhase::Open, false, false, true, 60.0, 0.1));
+    }
+
+    #[test]
+    fn explicitly_opened_door_is_not_closed_by_time() {
+        let mut forced_closing = ForcedClosing::default();
+
+        for _ in 0..100 {
+            assert!(!forced_closing.update(DoorPhase::Open, true, true, false, 10.0, 1.0));
+        }
+
+        // Zwangsschließen wirkt weiterhin
+        assert!(forced_closing.update(DoorPhase::Open, true, true, true, 10.0, 0.1));
+    }
+
+    #[test]
+    fn forced_closing_ends_once_closed_and_released() {
+        let mut forced_closing = ForcedClosing::default();
+
+        assert!(forced_closing.update(DoorPhase::Open, true, false, true, 60.0, 0.1));
+        assert!(forced_closing.update(DoorPhase::Closing, true, false, false, 60.0, 0.1));
+        // Ein abfallender Taster oder eine freie Lichtschranke beenden es nicht
+        assert!(forced_closing.update(DoorPhase::Closing, false, false, false, 60.0, 0.1));
+        assert!(forced_closing.update(DoorPhase::Closed, true, false, false, 60.0, 0.1));
+        assert!(!forced_closing.update(DoorPhase::Closed, false, false, false, 60.0, 0.1));
+    }
+}
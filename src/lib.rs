@@ -1,11 +1,15 @@
+use blink_clock::{add_blink_clock, BlinkClockSync};
+use bordnetz::add_bordnetz;
 use cockpit::add_cockpit;
-use doors::doors;
-use lights::add_lights;
+use couplings::{add_coupling, CouplingPresence, DoorCommand, DoorLoop, LightingSync};
+use doors::{
+    doors, DoorMaintenance, DoorSoundProfile, DoorTimingProfile, PassengerExchange, StopPosition,
+};
+use lights::{add_lights, LampFailure};
 use lotus_script::{
     graphics::textures::{Texture, TextureAction, TextureCreationOptions},
+    log,
     math::UVec2,
-    message::{send_message, Coupling, MessageMeta, MessageTarget},
-    prelude::MessageType,
     script,
     var::{get_var, set_var},
     vehicle::{rail_quality, surface_type},
@@ -13,9 +17,14 @@ use lotus_script::{
 };
 use misc::add_misc;
 use passenger_elements::passenger_elements;
+use ramp::add_ramp;
+use startup::add_startup;
 use systems_interface::{systems_interface, SystemStates};
 use traction::add_traction;
+use wiper::{add_wiper, WasherRefill};
 
+pub mod blink_clock;
+pub mod bordnetz;
 pub mod cockpit;
 pub mod cockpit_types;
 pub mod couplings;
@@ -24,38 +33,42 @@ pub mod input;
 pub mod lights;
 pub mod misc;
 pub mod passenger_elements;
+pub mod ramp;
+pub mod startup;
 pub mod systems_interface;
 pub mod traction;
+pub mod wiper;
 
 script!(ScriptGt6n);
 
 #[derive(Default)]
 pub struct ScriptGt6n {
+    systems: Option<SystemStates>,
     test_tex: Option<Texture>,
     // source_test_tex: Option<Texture>,
 }
 
-#[derive(Default, Clone, serde::Serialize, serde::Deserialize)]
-enum BlinkerState {
-    #[default]
-    Off,
-    On,
-}
-
-impl MessageType for BlinkerState {
-    const MESSAGE_META: MessageMeta = MessageMeta::new("mc", "blinker", None);
-}
-
 impl Script for ScriptGt6n {
     fn init(&mut self) {
-        systems_interface(SystemStates {
+        let blink_clock = add_blink_clock();
+
+        let systems = SystemStates {
             cockpit: add_cockpit(),
             passenger: passenger_elements(),
             traction: add_traction(),
-            lights: add_lights(),
+            lights: add_lights(blink_clock.clone()),
             misc: add_misc(),
-            doors: doors(),
-        });
+            doors: doors(blink_clock.clone()),
+            ramp: add_ramp(),
+            coupling: add_coupling(blink_clock),
+            bordnetz: add_bordnetz(),
+            startup: add_startup(),
+            wiper: add_wiper(),
+        };
+
+        systems_interface(systems.clone());
+
+        self.systems = Some(systems);
 
         set_var("Coupling_A_vis", &true);
         set_var("Coupling_B_vis", &true);
@@ -210,20 +223,6 @@ impl Script for ScriptGt6n {
 
         weichensounds();
 
-        if get_var::<f32>("A_CP_SW_Wischer") > 0.5 {
-            send_message(
-                if get_var::<f32>("A_CP_SW_Wischer") > 1.5 {
-                    &BlinkerState::On
-                } else {
-                    &BlinkerState::Off
-                },
-                [MessageTarget::AcrossCoupling {
-                    coupling: Coupling::Rear,
-                    cascade: true,
-                }],
-            )
-        };
-
         if let Some(rq) = rail_quality(0, 0) {
             set_var("aaa rq", &(rq as u8));
         }
@@ -234,15 +233,90 @@ impl Script for ScriptGt6n {
     }
 
     fn on_message(&mut self, msg: lotus_script::message::Message) {
-        msg.handle(|m: BlinkerState| {
-            match m {
-                BlinkerState::Off => {
-                    set_var("BlinkerRight", &0.0);
-                }
-                BlinkerState::On => {
-                    set_var("BlinkerRight", &1.0);
-                }
-            };
+        let Some(systems) = &self.systems else {
+            return;
+        };
+
+        msg.handle(|_: CouplingPresence| {
+            if let Some(coupling) = msg.source().coupling {
+                systems.coupling.receive_presence(coupling);
+            }
+            Ok(())
+        })
+        .ok();
+
+        msg.handle(|m: LightingSync| {
+            systems.coupling.receive_lighting(m);
+            Ok(())
+        })
+        .ok();
+
+        msg.handle(|m: BlinkClockSync| {
+            systems.coupling.receive_blink_clock(m);
+            Ok(())
+        })
+        .ok();
+
+        msg.handle(|m: DoorCommand| {
+            systems.coupling.receive_door_command(m);
+            Ok(())
+        })
+        .ok();
+
+        msg.handle(|m: DoorLoop| {
+            systems.coupling.receive_door_loop(m);
+            Ok(())
+        })
+        .ok();
+
+        msg.handle(|m: DoorTimingProfile| {
+            log::info!("Door timing profile: {:?}", m);
+            systems.doors.timing_profile.set(m);
+            Ok(())
+        })
+        .ok();
+
+        msg.handle(|m: DoorSoundProfile| {
+            log::info!("Door sound profile: {:?}", m);
+            systems.doors.sound_profile.set(m);
+            Ok(())
+        })
+        .ok();
+
+        msg.handle(|m: PassengerExchange| {
+            systems.doors.add_passenger_exchange(m);
+            Ok(())
+        })
+        .ok();
+
+        msg.handle(|m: StopPosition| {
+            systems.doors.stop_position(m);
+            Ok(())
+        })
+        .ok();
+
+        msg.handle(|m: LampFailure| {
+            match (
+                systems.cockpit.lamps.get(&m.lamp).map(|lamp| &lamp.failed),
+                systems.lights.failures.get(&m.lamp),
+            ) {
+                (Some(failed), _) | (None, Some(failed)) => failed.set(m.failed),
+                (None, None) => log::info!("Unbekannte Lampe: {}", m.lamp),
+            }
+            Ok(())
+        })
+        .ok();
+
+        msg.handle(|_: WasherRefill| {
+            log::info!("Waschanlage aufgefüllt");
+            systems.wiper.refill();
+            Ok(())
+        })
+        .ok();
+
+        msg.handle(|_: DoorMaintenance| {
+            log::info!("Türwartung durchgeführt");
+            systems.doors.reset_maintenance();
             Ok(())
         })
         .ok();
@@ -1,83 +1,345 @@
+use std::collections::HashMap;
+
 use lotus_rt::{spawn, wait};
-use lotus_script::{time::delta, var::set_var};
+use lotus_script::{
+    message::MessageMeta,
+    prelude::MessageType,
+    var::{get_var, set_var},
+};
 
 use lotus_rt_extra::shared::Shared;
 
+use crate::{
+    blink_clock::BlinkClockState, cockpit_types::OutsideLightSwitch, couplings::CouplingRole,
+};
+
 const BLINKER_FIRST_ON_TIME: f32 = 0.2;
 const BLINKER_FIRST_OFF_TIME: f32 = 0.56;
 const BLINKER_ON_TIME: f32 = 0.32;
 const BLINKER_OFF_TIME: f32 = 0.43;
 
+/// Flash interval of the high-level brake light during hazard braking
+const BREMSLICHT_HOCH_FLASH_INTERVAL: f32 = 0.25;
+
+/// Exterior lights that can fail, by variable name
+const FAILABLE_LIGHTS: [&str; 13] = [
+    "Standlicht_A",
+    "Abblendlicht_A",
+    "Fernlicht_A",
+    "Ruecklicht_A",
+    "Standlicht_B",
+    "Abblendlicht_B",
+    "Fernlicht_B",
+    "Ruecklicht_B",
+    "Rueckfahrlicht",
+    "Bremslicht",
+    "Bremslicht_Hoch",
+    "BlinkerRight",
+    "BlinkerLeft",
+];
+
 #[derive(Default, Debug, Clone)]
 pub struct LightState {
     pub voltage: Shared<f32>,
+    /// Supply of the main interior lighting, only present while the auxiliary converter runs
+    pub voltage_main: Shared<f32>,
+    /// Main interior lighting, fed by the auxiliary converter
     pub fahrgastraum: Shared<bool>,
-    pub stand: Shared<bool>,
-    pub abblend: Shared<bool>,
-    pub fern: Shared<bool>,
-    pub rueck: Shared<bool>,
+    /// Emergency lighting, fed by the battery. Stays on when the main supply fails.
+    pub notbeleuchtung: Shared<bool>,
+    /// Door-area lighting, one per door
+    pub tuerraum: Vec<Shared<bool>>,
+    pub end_a: EndLights,
+    pub end_b: EndLights,
+    pub parking_pattern: ParkingLightPattern,
     pub rueckfahr: Shared<bool>,
     pub brems: Shared<bool>,
+    /// Third, high-level brake light. Flashes during hazard braking.
+    pub brems_hoch: Shared<bool>,
+    pub gefahrbremsung: Shared<bool>,
     pub blinker_state: Shared<BlinkerState>,
+    /// Hazard lights switched on automatically after an emergency event
+    pub warn_auto: Shared<bool>,
+    /// Hazard lights switched on with the button. The press cancelling the automatic hazard
+    /// lights does not switch them on.
+    pub warn_manual: Shared<bool>,
+    /// Current phase of the indicator relay
+    pub blink_on: Shared<bool>,
     pub blinker_lampe_rechts: Shared<bool>,
     pub blinker_lampe_links: Shared<bool>,
     pub lm_warnblinker: Shared<bool>,
     pub cockpit_main: Shared<bool>,
     pub cockpit_begleiter: Shared<bool>,
     pub instrumente: Shared<bool>,
+    /// Failure state per exterior light, by variable name
+    pub failures: HashMap<String, Shared<bool>>,
+    /// At least one exterior light has failed
+    pub fault: Shared<bool>,
 }
 
-pub fn add_lights() -> LightState {
-    let channels = LightState::default();
-    let c = channels.clone();
+/// Sets or clears the failure of a cab lamp or an exterior light, e.g. from a scenario.
+/// `lamp` is the variable name, like `A_LM_Fernlicht` or `Bremslicht`.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct LampFailure {
+    pub lamp: String,
+    pub failed: bool,
+}
 
-    let mut blinkgeber = BlinkgeberState::default();
+impl MessageType for LampFailure {
+    const MESSAGE_META: MessageMeta = MessageMeta::new("gt6n", "lamp_failure", None);
+}
 
-    spawn(async move {
-        loop {
-            fn set_light(c: &LightState, b: &Shared<bool>, variable: &str) {
-                set_var(variable, &c.voltage.switch(b.get()));
+/// Random lamp failures, `rate` failures per hour of operation for the whole vehicle
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LampFaultGenerator {
+    rate: f32,
+    random: u32,
+}
+
+impl LampFaultGenerator {
+    const SEED: u32 = 0x9e37_79b9;
+
+    pub fn new(rate: f32) -> Self {
+        Self {
+            rate,
+            random: Self::SEED,
+        }
+    }
+
+    /// Index of the lamp failing in this tick, out of `lamps` intact lamps
+    pub fn update(&mut self, delta: f32, lamps: usize) -> Option<usize> {
+        // Die schwankenden Bildzeiten dienen als Zufallsquelle
+        self.random = self.random.rotate_left(5) ^ delta.to_bits();
+        if self.random == 0 {
+            self.random = Self::SEED;
+        }
+
+        let chance = self.next() as f32 / u32::MAX as f32;
+        if lamps == 0 || chance >= self.rate * delta / 3600.0 {
+            return None;
+        }
+
+        Some(self.next() as usize % lamps)
+    }
+
+    /// Xorshift
+    fn next(&mut self) -> u32 {
+        self.random ^= self.random << 13;
+        self.random ^= self.random >> 17;
+        self.random ^= self.random << 5;
+        self.random
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum VehicleEnd {
+    A,
+    B,
+}
+
+/// Head and tail lights at one end of the vehicle
+#[derive(Default, Debug, Clone)]
+pub struct EndLights {
+    pub stand: Shared<bool>,
+    pub abblend: Shared<bool>,
+    pub fern: Shared<bool>,
+    pub rueck: Shared<bool>,
+}
+
+impl LightState {
+    /// The exterior light with this variable name has failed
+    pub fn failed(&self, light: &str) -> bool {
+        self.failures.get(light).is_some_and(|failed| failed.get())
+    }
+}
+
+/// Values for [`EndLights`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct EndLightsTarget {
+    pub stand: bool,
+    pub abblend: bool,
+    pub fern: bool,
+    pub rueck: bool,
+}
+
+impl EndLights {
+    pub fn set(&self, target: EndLightsTarget) {
+        self.stand.set(target.stand);
+        self.abblend.set(target.abblend);
+        self.fern.set(target.fern);
+        self.rueck.set(target.rueck);
+    }
+}
+
+/// Head and tail lights of one end from the role of the vehicle in the coupled train. The
+/// leading vehicle leads with end A, coupled ends stay dark and the tail lights are only on
+/// at the very end of the train. `remote_tail_lights` comes from the leading vehicle.
+pub fn end_lights_target(
+    end: VehicleEnd,
+    coupled: bool,
+    role: CouplingRole,
+    switch: OutsideLightSwitch,
+    lichthupe: bool,
+    remote_tail_lights: Option<bool>,
+    parking_pattern: ParkingLightPattern,
+) -> EndLightsTarget {
+    let stand = switch != OutsideLightSwitch::Off;
+
+    match (role, remote_tail_lights) {
+        (CouplingRole::Leading, _) => {
+            let leading = end == VehicleEnd::A;
+            EndLightsTarget {
+                stand: stand && leading && !coupled,
+                abblend: matches!(
+                    switch,
+                    OutsideLightSwitch::Abblend | OutsideLightSwitch::Fern
+                ) && leading
+                    && !coupled,
+                fern: (switch == OutsideLightSwitch::Fern || lichthupe) && leading && !coupled,
+                rueck: stand && !leading && !coupled,
             }
+        }
+        (CouplingRole::Middle, Some(_)) => EndLightsTarget::default(),
+        (CouplingRole::Trailing, Some(tail_lights)) => EndLightsTarget {
+            rueck: tail_lights && !coupled,
+            ..Default::default()
+        },
+        // Ohne führendes Fahrzeug gilt das Abstellmuster
+        _ => EndLightsTarget {
+            stand: matches!(
+                parking_pattern,
+                ParkingLightPattern::Standlicht | ParkingLightPattern::StandUndRuecklicht
+            ),
+            rueck: matches!(
+                parking_pattern,
+                ParkingLightPattern::Ruecklicht | ParkingLightPattern::StandUndRuecklicht
+            ),
+            ..Default::default()
+        },
+    }
+}
 
-            fn running_blinker(c: &LightState, s: &mut BlinkgeberState) {
-                let prev_running = s.running;
+/// Lights of a parked vehicle without active cab, taken from `Cfg_ParkingLights`
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParkingLightPattern {
+    #[default]
+    Aus,
+    Standlicht,
+    Ruecklicht,
+    StandUndRuecklicht,
+}
 
-                s.running = c.blinker_state.get().is_active();
+impl From<i32> for ParkingLightPattern {
+    fn from(value: i32) -> Self {
+        match value {
+            1 => ParkingLightPattern::Standlicht,
+            2 => ParkingLightPattern::Ruecklicht,
+            3 => ParkingLightPattern::StandUndRuecklicht,
+            _ => ParkingLightPattern::Aus,
+        }
+    }
+}
 
-                if s.running != prev_running && s.running {
-                    s.first = true;
-                    s.on = true;
-                    s.timer = 0.0;
-                }
+pub fn add_lights(blink_clock: BlinkClockState) -> LightState {
+    let channels = LightState {
+        parking_pattern: ParkingLightPattern::from(get_var::<i32>("Cfg_ParkingLights")),
+        tuerraum: (0..4).map(|_| Shared::default()).collect(),
+        failures: FAILABLE_LIGHTS
+            .iter()
+            .map(|name| (name.to_string(), Shared::new(false)))
+            .collect(),
+        ..Default::default()
+    };
+    let c = channels.clone();
 
-                if s.update(delta()) {
-                    if s.on {
-                        set_var("Snd_Relais_Blinker_On", &true);
-                    } else {
-                        set_var("Snd_Relais_Blinker_Off", &true);
-                    }
-                }
+    spawn(async move {
+        let mut prev_blink_on = false;
+        let mut brems_hoch_registered = false;
+        let mut blinker_registered = false;
+
+        loop {
+            fn set_light(c: &LightState, b: &Shared<bool>, variable: &str) {
+                set_var(variable, &c.voltage.switch(b.get() && !c.failed(variable)));
             }
 
-            set_light(&c, &c.fahrgastraum, "Fahrgastraumbeleuchtung");
-            set_light(&c, &c.stand, "Standlicht");
-            set_light(&c, &c.abblend, "Abblendlicht");
-            set_light(&c, &c.fern, "Fernlicht");
-            set_light(&c, &c.rueck, "Ruecklicht");
+            set_var(
+                "Fahrgastraumbeleuchtung",
+                &c.voltage_main.switch(c.fahrgastraum.get()),
+            );
+            set_light(&c, &c.notbeleuchtung, "Notbeleuchtung");
+            for (i, tuerraum) in c.tuerraum.iter().enumerate() {
+                set_light(&c, tuerraum, &format!("Tuerraumbeleuchtung_{}", i + 1));
+            }
+            for (end, name) in [(&c.end_a, "A"), (&c.end_b, "B")] {
+                set_light(&c, &end.stand, &format!("Standlicht_{name}"));
+                set_light(&c, &end.abblend, &format!("Abblendlicht_{name}"));
+                set_light(&c, &end.fern, &format!("Fernlicht_{name}"));
+                set_light(&c, &end.rueck, &format!("Ruecklicht_{name}"));
+            }
+            // Die bisherigen Variablen ohne Endung folgen dem führenden Ende A
+            for (light, name) in [
+                (&c.end_a.stand, "Standlicht"),
+                (&c.end_a.abblend, "Abblendlicht"),
+                (&c.end_a.fern, "Fernlicht"),
+                (&c.end_a.rueck, "Ruecklicht"),
+            ] {
+                set_var(
+                    name,
+                    &c.voltage
+                        .switch(light.get() && !c.failed(&format!("{name}_A"))),
+                );
+            }
             set_light(&c, &c.rueckfahr, "Rueckfahrlicht");
             set_light(&c, &c.brems, "Bremslicht");
+
+            let flashing = c.brems.get() && c.gefahrbremsung.get();
+            blink_clock.track_user(flashing, &mut brems_hoch_registered);
+            let flash_on = blink_clock.is_on(
+                BREMSLICHT_HOCH_FLASH_INTERVAL * 0.5,
+                BREMSLICHT_HOCH_FLASH_INTERVAL,
+            );
+            c.brems_hoch
+                .set_only_on_change(c.brems.get() && (flash_on || !c.gefahrbremsung.get()));
+            set_light(&c, &c.brems_hoch, "Bremslicht_Hoch");
             set_light(&c, &c.cockpit_main, "A_CP_FstBelMain");
             set_light(&c, &c.cockpit_begleiter, "A_CP_FstBelBegleiter");
             set_light(&c, &c.instrumente, "A_CP_InstrBel");
 
-            running_blinker(&c, &mut blinkgeber);
+            c.fault
+                .set_only_on_change(c.failures.values().any(|failed| failed.get()));
+
+            // Mit einer ausgefallenen Blinkerlampe blinkt es doppelt so schnell
+            let state = c.blinker_state.get();
+            let lamp_failed = (state.is_links_active() && c.failed("BlinkerLeft"))
+                || (state.is_rechts_active() && c.failed("BlinkerRight"));
+            let speed = if lamp_failed { 2.0 } else { 1.0 };
+
+            blink_clock.track_user(state.is_active(), &mut blinker_registered);
+
+            let blink_on = state.is_active()
+                && blink_clock.is_on_with_first_cycle(
+                    BLINKER_FIRST_ON_TIME / speed,
+                    (BLINKER_FIRST_ON_TIME + BLINKER_FIRST_OFF_TIME) / speed,
+                    BLINKER_ON_TIME / speed,
+                    (BLINKER_ON_TIME + BLINKER_OFF_TIME) / speed,
+                );
+            c.blink_on.set_only_on_change(blink_on);
+
+            if blink_on != prev_blink_on {
+                if blink_on {
+                    set_var("Snd_Relais_Blinker_On", &true);
+                } else {
+                    set_var("Snd_Relais_Blinker_Off", &true);
+                }
+            }
+            prev_blink_on = blink_on;
 
             c.blinker_lampe_links
-                .set_only_on_change(blinkgeber.on && c.blinker_state.get().is_links_active());
+                .set_only_on_change(blink_on && c.blinker_state.get().is_links_active());
             c.blinker_lampe_rechts
-                .set_only_on_change(blinkgeber.on && c.blinker_state.get().is_rechts_active());
+                .set_only_on_change(blink_on && c.blinker_state.get().is_rechts_active());
             c.lm_warnblinker
-                .set_only_on_change(blinkgeber.on && c.blinker_state.get().is_warn_active());
+                .set_only_on_change(blink_on && c.blinker_state.get().is_warn_active());
 
             set_light(&c, &c.blinker_lampe_rechts, "BlinkerRight");
             set_light(&c, &c.blinker_lampe_links, "BlinkerLeft");
@@ -89,45 +351,7 @@ pub fn add_lights() -> LightState {
     channels
 }
 
-#[derive(Default, Debug, Clone)]
-struct BlinkgeberState {
-    running: bool,
-    on: bool,
-    first: bool,
-    timer: f32,
-}
-
-impl BlinkgeberState {
-    fn get_timer_limit(&self) -> f32 {
-        match (self.first, self.on) {
-            (true, true) => BLINKER_FIRST_ON_TIME,
-            (true, false) => BLINKER_FIRST_OFF_TIME,
-            (false, true) => BLINKER_ON_TIME,
-            (false, false) => BLINKER_OFF_TIME,
-        }
-    }
-
-    fn update(&mut self, delta: f32) -> bool {
-        if !self.running {
-            self.on = false;
-            return false;
-        }
-
-        self.timer += delta;
-        if self.timer > self.get_timer_limit() {
-            self.on = !self.on;
-            self.timer = 0.0;
-            if self.on {
-                self.first = false;
-            }
-            true
-        } else {
-            false
-        }
-    }
-}
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
 pub enum BlinkerState {
     #[default]
     Aus,
@@ -153,3 +377,129 @@ impl BlinkerState {
         *self == BlinkerState::Warn
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(
+        end: VehicleEnd,
+        coupled: bool,
+        role: CouplingRole,
+        remote_tail_lights: Option<bool>,
+    ) -> EndLightsTarget {
+        end_lights_target(
+            end,
+            coupled,
+            role,
+            OutsideLightSwitch::Abblend,
+            false,
+            remote_tail_lights,
+            ParkingLightPattern::StandUndRuecklicht,
+        )
+    }
+
+    const HEAD: EndLightsTarget = EndLightsTarget {
+        stand: true,
+        abblend: true,
+        fern: false,
+        rueck: false,
+    };
+    const TAIL: EndLightsTarget = EndLightsTarget {
+        stand: false,
+        abblend: false,
+        fern: false,
+        rueck: true,
+    };
+    const DARK: EndLightsTarget = EndLightsTarget {
+        stand: false,
+        abblend: false,
+        fern: false,
+        rueck: false,
+    };
+
+    #[test]
+    fn no_lamp_fails_without_rate() {
+        let mut generator = LampFaultGenerator::new(0.0);
+
+        assert!((0..100_000).all(|_| generator.update(0.02, 20).is_none()));
+    }
+
+    #[test]
+    fn lamps_fail_at_the_given_rate() {
+        // Im Mittel fällt alle 36 Sekunden eine Lampe aus
+        let mut generator = LampFaultGenerator::new(100.0);
+
+        let failures: Vec<_> = (0..180_000)
+            .filter_map(|_| generator.update(0.02, 20))
+            .collect();
+
+        assert!((50..150).contains(&failures.len()));
+        assert!(failures.iter().all(|&lamp| lamp < 20));
+        assert!(generator.update(1000.0, 0).is_none());
+    }
+
+    #[test]
+    fn single_leading_vehicle_has_head_and_tail_lights() {
+        assert_eq!(
+            target(VehicleEnd::A, false, CouplingRole::Leading, None),
+            HEAD
+        );
+        assert_eq!(
+            target(VehicleEnd::B, false, CouplingRole::Leading, None),
+            TAIL
+        );
+    }
+
+    #[test]
+    fn coupled_ends_stay_dark() {
+        // Führendes Fahrzeug, am Ende B gekuppelt
+        assert_eq!(
+            target(VehicleEnd::A, false, CouplingRole::Leading, None),
+            HEAD
+        );
+        assert_eq!(
+            target(VehicleEnd::B, true, CouplingRole::Leading, None),
+            DARK
+        );
+
+        // Mittleres Fahrzeug
+        for end in [VehicleEnd::A, VehicleEnd::B] {
+            assert_eq!(target(end, true, CouplingRole::Middle, Some(true)), DARK);
+        }
+    }
+
+    #[test]
+    fn last_vehicle_shows_tail_lights_of_the_leader() {
+        assert_eq!(
+            target(VehicleEnd::A, true, CouplingRole::Trailing, Some(true)),
+            DARK
+        );
+        assert_eq!(
+            target(VehicleEnd::B, false, CouplingRole::Trailing, Some(true)),
+            TAIL
+        );
+        assert_eq!(
+            target(VehicleEnd::B, false, CouplingRole::Trailing, Some(false)),
+            DARK
+        );
+    }
+
+    #[test]
+    fn without_leader_the_parking_pattern_applies() {
+        let parked = EndLightsTarget {
+            stand: true,
+            rueck: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            target(VehicleEnd::A, false, CouplingRole::Alone, None),
+            parked
+        );
+        assert_eq!(
+            target(VehicleEnd::B, true, CouplingRole::Trailing, None),
+            parked
+        );
+    }
+}
@@ -1,15 +1,50 @@
 use lotus_rt_extra::{
-    cockpit_simple::{timed_button, TimedButtonProperties},
+    cockpit_simple::{switch, timed_button, SwitchProperties, TimedButtonProperties},
     shared::Shared,
 };
 
 #[derive(Debug, Clone)]
 pub struct PassengerElementsState {
+    /// Door buttons of the original model, events `DoorButton{n}`. They act like the inside
+    /// buttons.
     pub door_buttons: Vec<Shared<bool>>,
+    pub door_buttons_inside: Vec<Shared<bool>>,
+    pub door_buttons_outside: Vec<Shared<bool>>,
+    pub emergency_handles_inside: Vec<Shared<bool>>,
+    pub emergency_handles_outside: Vec<Shared<bool>>,
+    pub wheelchair_request: Shared<bool>,
+    pub notbremse: Shared<bool>,
+}
+
+/// Illumination of a door button, written as number to `Door_{n}_BtnLgtI` and `Door_{n}_BtnLgtO`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DoorButtonLight {
+    #[default]
+    Off = 0,
+    Released = 1,
+    Acknowledged = 2,
+    Locked = 3,
+    OutOfService = 4,
 }
 
 pub fn passenger_elements() -> PassengerElementsState {
-    let door_buttons: Vec<_> = (0..4)
+    let door_button = |door_number: usize, side: &str| -> Shared<bool> {
+        let button = timed_button(
+            TimedButtonProperties::builder()
+                .input_event(format!("DoorButton{}{}", side, door_number + 1))
+                .time_staying_on(2.0)
+                .time_before_pressable_again(1.0)
+                .build(),
+        );
+
+        button
+            .to_float()
+            .var_writer(format!("A_Door_{}_Btn{}", door_number + 1, side));
+
+        button
+    };
+
+    let door_buttons = (0..4)
         .map(|i| {
             timed_button(
                 TimedButtonProperties::builder()
@@ -21,5 +56,49 @@ pub fn passenger_elements() -> PassengerElementsState {
         })
         .collect();
 
-    PassengerElementsState { door_buttons }
+    let door_buttons_inside = (0..4).map(|i| door_button(i, "I")).collect();
+    let door_buttons_outside = (0..4).map(|i| door_button(i, "O")).collect();
+
+    let emergency_handle = |door_number: usize, side: &str| -> Shared<bool> {
+        switch(
+            SwitchProperties::builder()
+                .toggle_event(format!("DoorEmergency{}{}", side, door_number + 1))
+                .animation_var(format!(
+                    "A_Door_{}_Notentriegelung{}",
+                    door_number + 1,
+                    side
+                ))
+                .sound_switch(format!("Snd_Door_{}_Notentriegelung", door_number + 1))
+                .build(),
+        )
+    };
+
+    let emergency_handles_inside = (0..4).map(|i| emergency_handle(i, "I")).collect();
+    let emergency_handles_outside = (0..4).map(|i| emergency_handle(i, "O")).collect();
+
+    let wheelchair_request = timed_button(
+        TimedButtonProperties::builder()
+            .input_event("WheelchairRequest")
+            .time_staying_on(2.0)
+            .time_before_pressable_again(1.0)
+            .build(),
+    );
+
+    let notbremse = switch(
+        SwitchProperties::builder()
+            .toggle_event("PassengerEmergencyBrake")
+            .animation_var("A_Notbremse")
+            .sound_switch("Snd_Notbremse")
+            .build(),
+    );
+
+    PassengerElementsState {
+        door_buttons,
+        door_buttons_inside,
+        door_buttons_outside,
+        emergency_handles_inside,
+        emergency_handles_outside,
+        wheelchair_request,
+        notbremse,
+    }
 }
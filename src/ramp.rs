@@ -0,0 +1,58 @@
+use lotus_rt::{spawn, wait};
+use lotus_script::{time::delta, var::set_var};
+
+use lotus_rt_extra::shared::Shared;
+
+/// The ramp is mounted at the multi-purpose door (door 2)
+pub const RAMP_DOOR: usize = 1;
+
+const RAMP_TIME: f32 = 4.0;
+
+#[derive(Default, Debug, Clone)]
+pub struct RampState {
+    /// Ramp requested by a passenger or the driver
+    pub requested: Shared<bool>,
+    pub deploy: Shared<bool>,
+    pub position: Shared<f32>,
+    pub retracted: Shared<bool>,
+}
+
+pub fn add_ramp() -> RampState {
+    let channels = RampState {
+        retracted: Shared::new(true),
+        ..Default::default()
+    };
+    let c = channels.clone();
+
+    spawn(async move {
+        let mut prev_moving = false;
+
+        loop {
+            let position = c.position.get();
+            let target = if c.deploy.get() { 1.0 } else { 0.0 };
+
+            let new_position = if target > position {
+                (position + delta() / RAMP_TIME).min(target)
+            } else {
+                (position - delta() / RAMP_TIME).max(target)
+            };
+
+            let moving = new_position != position;
+
+            set_var("Snd_Ramp_Motor", &moving);
+            if prev_moving && !moving {
+                set_var("Snd_Ramp_End", &true);
+            }
+            prev_moving = moving;
+
+            c.position.set_only_on_change(new_position);
+            c.retracted.set_only_on_change(new_position <= 0.0);
+
+            set_var("Ramp_Pos", &new_position);
+
+            wait::next_tick().await;
+        }
+    });
+
+    channels
+}
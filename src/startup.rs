@@ -0,0 +1,187 @@
+use lotus_rt::{spawn, wait};
+use lotus_script::{log, time::delta, var::set_var};
+
+use lotus_rt_extra::shared::Shared;
+
+/// Duration of the self-test of the control electronics
+const SELF_TEST_TIME: f32 = 4.0;
+/// Time for raising or lowering the pantograph
+const PANTOGRAPH_TIME: f32 = 6.0;
+/// Precharging of the traction converters after closing the main switch
+const READY_DELAY: f32 = 2.0;
+/// Time the control electronics need to shut down after the cab key is removed
+const SHUTDOWN_TIME: f32 = 3.0;
+
+/// Stages of the start-up (Aufrüsten). Shutting down (Abrüsten) runs backwards through them.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum StartupStage {
+    /// Battery main switch off, vehicle dead
+    #[default]
+    Off,
+    /// Low-voltage network on, control electronics off
+    Battery,
+    SelfTest,
+    /// Control electronics running, pantograph down
+    Control,
+    /// Pantograph raised, main switch open
+    Pantograph,
+    /// Main switch closed, traction converters precharging
+    MainSwitch,
+    Ready,
+}
+
+impl From<StartupStage> for i32 {
+    fn from(value: StartupStage) -> Self {
+        match value {
+            StartupStage::Off => 0,
+            StartupStage::Battery => 1,
+            StartupStage::SelfTest => 2,
+            StartupStage::Control => 3,
+            StartupStage::Pantograph => 4,
+            StartupStage::MainSwitch => 5,
+            StartupStage::Ready => 6,
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone)]
+pub struct StartupState {
+    pub battery_switch: Shared<bool>,
+    pub cab_key: Shared<bool>,
+    pub pantograph_up_request: Shared<bool>,
+    pub pantograph_down_request: Shared<bool>,
+    pub main_switch_on_request: Shared<bool>,
+    pub main_switch_off_request: Shared<bool>,
+
+    pub stage: Shared<StartupStage>,
+    /// Position of the pantograph, 0.0 (down) to 1.0 (up)
+    pub pantograph: Shared<f32>,
+    pub main_switch: Shared<bool>,
+    pub self_test: Shared<bool>,
+    /// Control electronics are running
+    pub control: Shared<bool>,
+    pub ready: Shared<bool>,
+    /// Parking mode: interior lighting and auxiliaries are off until the vehicle is woken up
+    pub parking: Shared<bool>,
+}
+
+pub fn add_startup() -> StartupState {
+    let channels = StartupState::default();
+    let c = channels.clone();
+
+    spawn(async move {
+        let mut pantograph_raised = false;
+        let mut stage_timer = 0.0;
+        let mut shutdown_timer = 0.0;
+
+        loop {
+            let stage = c.stage.get();
+            let battery = c.battery_switch.get();
+            let key = c.cab_key.get();
+
+            // Stromabnehmer ------------------------------------------
+
+            if !battery || !key || c.pantograph_down_request.get() {
+                pantograph_raised = false;
+            } else if c.pantograph_up_request.get() && stage >= StartupStage::Control {
+                if !pantograph_raised {
+                    set_var("Snd_Pantograph_Up", &true);
+                }
+                pantograph_raised = true;
+            }
+
+            let pantograph = c.pantograph.get();
+            let new_pantograph = if pantograph_raised {
+                (pantograph + delta() / PANTOGRAPH_TIME).min(1.0)
+            } else {
+                (pantograph - delta() / PANTOGRAPH_TIME).max(0.0)
+            };
+            if pantograph > 0.0 && new_pantograph <= 0.0 {
+                set_var("Snd_Pantograph_Down", &true);
+            }
+            c.pantograph.set_only_on_change(new_pantograph);
+            set_var("A_Pantograph", &new_pantograph);
+
+            // Hauptschalter ------------------------------------------
+
+            let line_voltage = new_pantograph >= 1.0;
+
+            if !battery || !key || !line_voltage || c.main_switch_off_request.get() {
+                if c.main_switch.get() {
+                    set_var("Snd_Hauptschalter_Aus", &true);
+                }
+                c.main_switch.set_only_on_change(false);
+            } else if c.main_switch_on_request.get() && stage >= StartupStage::Pantograph {
+                if !c.main_switch.get() {
+                    set_var("Snd_Hauptschalter_Ein", &true);
+                }
+                c.main_switch.set_only_on_change(true);
+            }
+
+            let main_switch = c.main_switch.get();
+
+            // Stufen -------------------------------------------------
+
+            if stage == StartupStage::Control && !key {
+                shutdown_timer += delta();
+            } else {
+                shutdown_timer = 0.0;
+            }
+
+            let new_stage = match stage {
+                _ if !battery => StartupStage::Off,
+                StartupStage::Off => StartupStage::Battery,
+                StartupStage::Battery if key => StartupStage::SelfTest,
+                StartupStage::Battery => StartupStage::Battery,
+                StartupStage::SelfTest if !key => StartupStage::Battery,
+                StartupStage::SelfTest if stage_timer >= SELF_TEST_TIME => StartupStage::Control,
+                StartupStage::SelfTest => StartupStage::SelfTest,
+                StartupStage::Control if shutdown_timer >= SHUTDOWN_TIME => StartupStage::Battery,
+                StartupStage::Control if line_voltage => StartupStage::Pantograph,
+                StartupStage::Control => StartupStage::Control,
+                StartupStage::Pantograph if !line_voltage => StartupStage::Control,
+                StartupStage::Pantograph if main_switch => StartupStage::MainSwitch,
+                StartupStage::Pantograph => StartupStage::Pantograph,
+                StartupStage::MainSwitch | StartupStage::Ready if !main_switch => {
+                    StartupStage::Pantograph
+                }
+                StartupStage::MainSwitch if stage_timer >= READY_DELAY => StartupStage::Ready,
+                StartupStage::MainSwitch => StartupStage::MainSwitch,
+                StartupStage::Ready => StartupStage::Ready,
+            };
+
+            if new_stage != stage {
+                log::info!("Aufrüsten: {:?} -> {:?}", stage, new_stage);
+                stage_timer = 0.0;
+
+                match (stage, new_stage) {
+                    (StartupStage::Off, StartupStage::Battery) => {
+                        set_var("Snd_Startup_Battery", &true)
+                    }
+                    (_, StartupStage::SelfTest) => set_var("Snd_Startup_SelfTest", &true),
+                    (_, StartupStage::Ready) => set_var("Snd_Startup_Ready", &true),
+                    (StartupStage::Control, StartupStage::Battery) => {
+                        set_var("Snd_Shutdown", &true)
+                    }
+                    (_, StartupStage::Off) => set_var("Snd_Shutdown_Battery", &true),
+                    _ => {}
+                }
+            } else {
+                stage_timer += delta();
+            }
+
+            c.stage.set_only_on_change(new_stage);
+            c.self_test
+                .set_only_on_change(new_stage == StartupStage::SelfTest);
+            c.control
+                .set_only_on_change(new_stage >= StartupStage::Control);
+            c.ready.set_only_on_change(new_stage == StartupStage::Ready);
+
+            set_var("Startup_Stage", &i32::from(new_stage));
+
+            wait::next_tick().await;
+        }
+    });
+
+    channels
+}
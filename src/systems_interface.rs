@@ -1,20 +1,44 @@
 use lotus_rt::{spawn, wait};
 use lotus_rt_extra::{
+    cockpit_simple::ButtonTwoSidedSpringLoadedState,
     doors::DoorControlMode,
     shared::{multiple_on_change, Shared},
 };
-use lotus_script::{log, var::set_var};
+use lotus_script::{
+    log,
+    time::delta,
+    var::{get_var, set_var},
+};
 
 use crate::{
+    bordnetz::BordnetzState,
     cockpit::CockpitState,
-    cockpit_types::{BlinkerSwitch, DoorSwitch, OutsideLightSwitch, RichtungswenderState},
-    doors::DoorsState,
-    lights::{BlinkerState, LightState},
+    cockpit_types::{
+        BlinkerSwitch, DoorModeSwitch, DoorSwitch, OutsideLightSwitch, RichtungswenderState,
+    },
+    couplings::{CouplingState, DoorCommand, LightingSync},
+    doors::{AutoRelease, DoorPhase, DoorsState},
+    lights::{end_lights_target, BlinkerState, LampFaultGenerator, LightState, VehicleEnd},
     misc::MiscState,
-    passenger_elements::PassengerElementsState,
+    passenger_elements::{DoorButtonLight, PassengerElementsState},
+    ramp::{RampState, RAMP_DOOR},
+    startup::StartupState,
     traction::{TractionDirection, TractionState},
+    wiper::{WiperMode, WiperState},
 };
 
+/// Default maximum time the headlight flasher keeps the high beam on
+const LICHTHUPE_MAX_DURATION: f32 = 3.0;
+
+/// Rail quality values that mark a switch, as in `weichensounds`
+const SWITCH_RAIL_QUALITY: std::ops::RangeInclusive<i32> = 2..=4;
+
+/// Default time without active cab before the vehicle goes into parking mode
+const PARKING_TIMEOUT: f32 = 600.0;
+
+/// Brake effort from which on the brake lights are lit
+const BRAKE_LIGHT_MIN_FORCE: f32 = 0.05;
+
 #[derive(Debug, Clone)]
 pub struct SystemStates {
     pub cockpit: CockpitState,
@@ -23,12 +47,19 @@ pub struct SystemStates {
     pub lights: LightState,
     pub misc: MiscState,
     pub doors: DoorsState,
+    pub ramp: RampState,
+    pub coupling: CouplingState,
+    pub bordnetz: BordnetzState,
+    pub startup: StartupState,
+    pub wiper: WiperState,
 }
 
 #[derive(Debug, Clone, Default)]
 struct InterfaceState {
     cockpit_a_active: Shared<bool>,
     cockpit_a_drive: Shared<bool>,
+    /// High beam requested by the headlight flasher
+    lichthupe: Shared<bool>,
 }
 
 #[derive(Clone)]
@@ -45,15 +76,79 @@ pub fn systems_interface(channels: SystemStates) {
             cockpit_a_active: channels_clone
                 .cockpit
                 .richtungswender
-                .process(|r| !matches!(r, RichtungswenderState::O), false),
-            cockpit_a_drive: channels_clone.cockpit.richtungswender.process(
-                |r| matches!(r, RichtungswenderState::V | RichtungswenderState::R),
-                false,
-            ),
+                .process(|r| !matches!(r, RichtungswenderState::O), false)
+                .and(&channels_clone.startup.control),
+            cockpit_a_drive: channels_clone
+                .cockpit
+                .richtungswender
+                .process(
+                    |r| matches!(r, RichtungswenderState::V | RichtungswenderState::R),
+                    false,
+                )
+                .and(&channels_clone.startup.ready),
+            lichthupe: Shared::default(),
         },
     };
 
-    state.systems.lights.voltage.set(1.0);
+    // Aufrüsten ---------------------------------------
+
+    startup_controls(&state);
+
+    // Bordnetz ---------------------------------------
+
+    // Der Umformer hängt hinter dem Hauptschalter
+    state
+        .systems
+        .startup
+        .main_switch
+        .forward(&state.systems.bordnetz.line_voltage);
+
+    state
+        .systems
+        .startup
+        .main_switch
+        .to_float()
+        .forward(&state.systems.traction.line_voltage);
+
+    for voltage in [
+        &state.systems.lights.voltage,
+        &state.systems.traction.voltage,
+        &state.systems.wiper.voltage,
+    ] {
+        state.systems.bordnetz.voltage.forward(voltage);
+    }
+
+    state
+        .systems
+        .bordnetz
+        .voltage
+        .multiply(&state.systems.bordnetz.converter_active.to_float())
+        .forward(&state.systems.lights.voltage_main);
+
+    // Die Meldeleuchten werden erst von der Steuerelektronik versorgt
+    state
+        .systems
+        .bordnetz
+        .voltage
+        .multiply(
+            &state
+                .systems
+                .startup
+                .self_test
+                .or(&state.systems.startup.control)
+                .to_float(),
+        )
+        .forward(&state.systems.cockpit.voltage);
+
+    spawn(bordnetz_load(state.clone()));
+
+    spawn(parking_mode(state.clone()));
+
+    state
+        .systems
+        .startup
+        .parking
+        .forward(&state.systems.bordnetz.standby);
 
     traction_control(&state);
 
@@ -65,9 +160,32 @@ pub fn systems_interface(channels: SystemStates) {
 
     spawn(sanding_unit(state.clone()));
 
+    state
+        .systems
+        .passenger
+        .notbremse
+        .forward(&state.systems.traction.emergency_brake);
+
+    spawn(lichthupe(state.clone()));
+
+    state.interface.lichthupe.trigger_sound("Snd_Lichthupe");
+
     outside_lights(&state);
+    brake_lights(&state);
     blinker_lights(&state);
 
+    spawn(auto_hazard_lights(state.clone()));
+
+    spawn(blinker_self_cancel(state.clone()));
+
+    spawn(lamp_faults(state.clone()));
+
+    spawn(lighting_sync(
+        state.systems.lights.clone(),
+        state.systems.cockpit.clone(),
+        state.systems.coupling.clone(),
+    ));
+
     inside_lights(&state);
 
     // Cockpit ---------------------------------------
@@ -86,8 +204,24 @@ pub fn systems_interface(channels: SystemStates) {
         .cockpit
         .lightcheck
         .and(&state.interface.cockpit_a_active)
+        .or(&state.systems.startup.self_test)
         .forward(&state.systems.cockpit.lm_check);
 
+    state
+        .systems
+        .lights
+        .fault
+        .and(&state.interface.cockpit_a_active)
+        .forward(&state.systems.cockpit.lm_stoerung);
+
+    state
+        .systems
+        .startup
+        .main_switch
+        .process(|m| !*m, true)
+        .and(&state.systems.startup.control)
+        .forward(&state.systems.cockpit.lm_hauptschalter);
+
     state
         .systems
         .traction
@@ -102,10 +236,98 @@ pub fn systems_interface(channels: SystemStates) {
         state.systems.cockpit.clone(),
         state.systems.passenger.clone(),
         state.systems.traction.clone(),
+        state.systems.ramp.clone(),
+        state.systems.coupling.clone(),
+    ));
+
+    // Das Fahrzeug mit aktivem Führerstand führt den Zugverband
+    state
+        .interface
+        .cockpit_a_active
+        .forward(&state.systems.coupling.lead);
+
+    spawn(wheelchair_ramp(
+        state.systems.doors.clone(),
+        state.systems.cockpit.clone(),
+        state.systems.passenger.clone(),
+        state.systems.ramp.clone(),
+    ));
+
+    state
+        .systems
+        .ramp
+        .retracted
+        .process(|r| !*r, false)
+        .forward(&state.systems.doors.doors_with_controller[RAMP_DOOR].hold_open);
+
+    spawn(door_emergency_release(
+        state.systems.doors.clone(),
+        state.systems.cockpit.clone(),
+        state.systems.passenger.clone(),
     ));
 
+    traction_interlock(&state);
+
+    state
+        .systems
+        .doors
+        .stop_request
+        .and(&state.interface.cockpit_a_active)
+        .forward(&state.systems.cockpit.lm_haltewunsch);
+
+    state
+        .systems
+        .doors
+        .stop_request
+        .trigger_sound("Snd_Haltewunsch");
+
+    state
+        .systems
+        .doors
+        .maintenance_due
+        .and(&state.interface.cockpit_a_active)
+        .forward(&state.systems.cockpit.lm_wartung);
+
+    state
+        .systems
+        .doors
+        .emergency_released
+        .and(&state.interface.cockpit_a_active)
+        .forward(&state.systems.cockpit.lm_notentriegelung);
+
+    state
+        .systems
+        .cockpit
+        .lm_notentriegelung
+        .loop_sound("Snd_CP_A_DoorEmergency".to_string());
+
+    for (door, lm_tuer) in state
+        .systems
+        .doors
+        .doors_with_controller
+        .iter()
+        .zip(state.systems.cockpit.lm_tueren_notentriegelung.iter())
+    {
+        door.emergency_release.forward(lm_tuer);
+    }
+
+    for (door, lm_tuer) in state
+        .systems
+        .doors
+        .doors_with_controller
+        .iter()
+        .zip(state.systems.cockpit.lm_tueren.iter())
+    {
+        door.closed
+            .process(|closed| !*closed, false)
+            .and(&state.interface.cockpit_a_active)
+            .forward(lm_tuer);
+    }
+
     // Misc Systems ---------------------------------------
 
+    wiper(&state);
+
     state
         .interface
         .cockpit_a_active
@@ -117,6 +339,217 @@ pub fn systems_interface(channels: SystemStates) {
         .loop_sound("Snd_Cabin_IdleVR".to_string());
 }
 
+/// Parking mode after `Cfg_ParkingTimeout` seconds without active cab, with the vehicle
+/// at rest and the spring brake applied. A door button press wakes the vehicle up.
+async fn parking_mode(state: Interface) {
+    let timeout = match get_var::<f32>("Cfg_ParkingTimeout") {
+        t if t > 0.0 => t,
+        _ => PARKING_TIMEOUT,
+    };
+
+    let parking = &state.systems.startup.parking;
+    let passenger = &state.systems.passenger;
+
+    let mut timer = 0.0;
+
+    loop {
+        let door_button = passenger
+            .door_buttons
+            .iter()
+            .chain(passenger.door_buttons_inside.iter())
+            .chain(passenger.door_buttons_outside.iter())
+            .any(|button| button.get());
+
+        let at_rest = state.systems.traction.speed.get().abs() < 0.01
+            && state.systems.traction.federspeicher.get();
+
+        if state.interface.cockpit_a_active.get() || door_button || !at_rest {
+            timer = 0.0;
+            if parking.get() {
+                log::info!("Abstellmodus beendet");
+            }
+            parking.set_only_on_change(false);
+        } else {
+            timer += delta();
+            if timer >= timeout && !parking.get() {
+                log::info!("Abstellmodus");
+                parking.set(true);
+            }
+        }
+
+        wait::next_tick().await;
+    }
+}
+
+fn startup_controls(state: &Interface) {
+    let cockpit = &state.systems.cockpit;
+    let startup = &state.systems.startup;
+
+    cockpit.batterie.forward(&startup.battery_switch);
+    cockpit.batterie.forward(&state.systems.bordnetz.battery_on);
+    cockpit.schluessel.forward(&startup.cab_key);
+
+    cockpit
+        .pantograph
+        .process(
+            |p| matches!(p, ButtonTwoSidedSpringLoadedState::Plus),
+            false,
+        )
+        .forward(&startup.pantograph_up_request);
+    cockpit
+        .pantograph
+        .process(
+            |p| matches!(p, ButtonTwoSidedSpringLoadedState::Minus),
+            false,
+        )
+        .forward(&startup.pantograph_down_request);
+    cockpit
+        .hauptschalter
+        .process(
+            |h| matches!(h, ButtonTwoSidedSpringLoadedState::Plus),
+            false,
+        )
+        .forward(&startup.main_switch_on_request);
+    cockpit
+        .hauptschalter
+        .process(
+            |h| matches!(h, ButtonTwoSidedSpringLoadedState::Minus),
+            false,
+        )
+        .forward(&startup.main_switch_off_request);
+}
+
+/// Random failures of cab lamps and exterior lights, `Cfg_LampFailureRate` failures per hour
+/// of operation. Without the config no lamp fails by itself.
+async fn lamp_faults(state: Interface) {
+    let mut generator = LampFaultGenerator::new(get_var::<f32>("Cfg_LampFailureRate").max(0.0));
+
+    loop {
+        // Lampen fallen nur unter Spannung aus
+        if state.systems.bordnetz.voltage.get() > 0.0 {
+            let intact: Vec<_> = state
+                .systems
+                .cockpit
+                .lamps
+                .iter()
+                .map(|(name, lamp)| (name, &lamp.failed))
+                .chain(state.systems.lights.failures.iter())
+                .filter(|(_, failed)| !failed.get())
+                .collect();
+
+            if let Some(lamp) = generator.update(delta(), intact.len()) {
+                let (name, failed) = intact[lamp];
+                log::info!("Lampe ausgefallen: {}", name);
+                failed.set(true);
+            }
+        }
+
+        wait::next_tick().await;
+    }
+}
+
+/// Current draw of the consumers on the Bordnetz. Dimmed consumers draw less.
+async fn bordnetz_load(state: Interface) {
+    let lights = &state.systems.lights;
+    let cockpit = &state.systems.cockpit;
+    let bordnetz = &state.systems.bordnetz;
+
+    loop {
+        let current = |consumers: &[(bool, f32)]| -> f32 {
+            consumers
+                .iter()
+                .filter(|(on, _)| *on)
+                .map(|(_, current)| current)
+                .sum::<f32>()
+                * bordnetz.voltage.get()
+        };
+
+        // Ausgefallene Lampen nehmen keinen Strom auf
+        let light = |on: &Shared<bool>, variable: &str| on.get() && !lights.failed(variable);
+
+        let mut consumers_lights = vec![
+            (light(&lights.notbeleuchtung, "Notbeleuchtung"), 2.0),
+            (light(&lights.rueckfahr, "Rueckfahrlicht"), 1.0),
+            (light(&lights.brems, "Bremslicht"), 2.0),
+            (light(&lights.brems_hoch, "Bremslicht_Hoch"), 0.5),
+            (light(&lights.blinker_lampe_links, "BlinkerLeft"), 2.0),
+            (light(&lights.blinker_lampe_rechts, "BlinkerRight"), 2.0),
+            (lights.cockpit_main.get(), 1.0),
+            (lights.cockpit_begleiter.get(), 0.5),
+            (lights.instrumente.get(), 0.5),
+        ];
+        if bordnetz.converter_active.get() {
+            consumers_lights.push((lights.fahrgastraum.get(), 16.0));
+        }
+        for tuerraum in &lights.tuerraum {
+            consumers_lights.push((tuerraum.get(), 1.0));
+        }
+        for (end, name) in [(&lights.end_a, "A"), (&lights.end_b, "B")] {
+            consumers_lights.push((light(&end.stand, &format!("Standlicht_{name}")), 0.5));
+            consumers_lights.push((light(&end.abblend, &format!("Abblendlicht_{name}")), 3.0));
+            consumers_lights.push((light(&end.fern, &format!("Fernlicht_{name}")), 3.0));
+            consumers_lights.push((light(&end.rueck, &format!("Ruecklicht_{name}")), 0.5));
+        }
+        bordnetz
+            .current_lights
+            .set_only_on_change(current(&consumers_lights));
+
+        // Drei Magnetschienenbremsen
+        bordnetz
+            .current_mg
+            .set_only_on_change(current(&[(state.systems.traction.mg.get(), 3.0 * 12.0)]));
+
+        let doors_moving = state
+            .systems
+            .doors
+            .doors_with_controller
+            .iter()
+            .filter(|door| door.moving.get())
+            .count();
+        bordnetz
+            .current_doors
+            .set_only_on_change(doors_moving as f32 * 4.0 * bordnetz.voltage.get());
+
+        // Wischermotor und Waschpumpe
+        let wiper = &state.systems.wiper;
+        bordnetz.current_wiper.set_only_on_change(current(&[
+            (wiper.motor.get(), 4.0),
+            (wiper.pump.get(), 2.5),
+        ]));
+
+        let lamp_count = cockpit.lamps.values().filter(|lamp| lamp.lit.get()).count();
+        bordnetz
+            .current_lamps
+            .set_only_on_change(lamp_count as f32 * 0.1 * bordnetz.voltage.get());
+
+        wait::next_tick().await;
+    }
+}
+
+fn wiper(state: &Interface) {
+    let cockpit_a_active = state.interface.cockpit_a_active.clone();
+    let switch_wischer = state.systems.cockpit.scheibenwischer.clone();
+    let mode = state.systems.wiper.mode.clone();
+
+    multiple_on_change(
+        &[&cockpit_a_active.clone(), &switch_wischer.clone()],
+        move || {
+            mode.set(if cockpit_a_active.get() {
+                WiperMode::from(switch_wischer.get())
+            } else {
+                WiperMode::Aus
+            });
+        },
+    );
+
+    state
+        .systems
+        .cockpit
+        .waschanlage
+        .and(&state.interface.cockpit_a_active)
+        .forward(&state.systems.wiper.washer);
+}
+
 async fn federspeicher(cockpit: CockpitState, traction: TractionState, interface: InterfaceState) {
     let mut prev = false;
     loop {
@@ -205,66 +638,286 @@ fn traction_control(state: &Interface) {
     set_var("abs", &true);
 }
 
+fn traction_interlock(state: &Interface) {
+    let cockpit_a_active = state.interface.cockpit_a_active.clone();
+    let notstart = state.systems.cockpit.notstart.clone();
+    let all_closed = state.systems.doors.all_closed.clone();
+    let remote_doors_closed = state.systems.coupling.remote_doors_closed.clone();
+    let released = state.systems.doors.released.clone();
+    let emergency_released = state.systems.doors.emergency_released.clone();
+    let ramp_retracted = state.systems.ramp.retracted.clone();
+    let ready = state.systems.startup.ready.clone();
+
+    let blocked = state.systems.traction.blocked.clone();
+    let notstart_active = Shared::new(false);
+
+    notstart_active.on_refresh(|active| {
+        if *active {
+            log::info!("Notstart: Türschleife überbrückt");
+        }
+    });
+
+    notstart_active.forward(&state.systems.cockpit.lm_notstart);
+
+    multiple_on_change(
+        &[
+            &cockpit_a_active.clone(),
+            &notstart.clone(),
+            &all_closed.clone(),
+            &remote_doors_closed.clone(),
+            &released.clone(),
+            &emergency_released.clone(),
+            &ramp_retracted.clone(),
+            &ready.clone(),
+        ],
+        move || {
+            // Die Türschleife läuft über die Kupplung durch den ganzen Zug
+            let door_loop_open = released.get() || !all_closed.get() || !remote_doors_closed.get();
+            let overridden = cockpit_a_active.get() && notstart.get().is_in();
+
+            notstart_active.set_only_on_change(overridden && door_loop_open);
+
+            // Notentriegelung und ausgefahrene Rampe können mit Notstart nicht überbrückt werden
+            blocked.set_only_on_change(
+                !ready.get()
+                    || emergency_released.get()
+                    || !ramp_retracted.get()
+                    || (door_loop_open && !overridden),
+            );
+        },
+    );
+}
+
+/// The flasher switches the high beam on whatever the switch position is,
+/// for at most `Cfg_LichthupeMaxDuration` seconds per press
+async fn lichthupe(state: Interface) {
+    let max_duration = match get_var::<f32>("Cfg_LichthupeMaxDuration") {
+        d if d > 0.0 => d,
+        _ => LICHTHUPE_MAX_DURATION,
+    };
+
+    let mut timer = 0.0;
+
+    loop {
+        let pressed =
+            state.systems.cockpit.lichthupe.get() && state.interface.cockpit_a_active.get();
+
+        if pressed {
+            timer += delta();
+        } else {
+            timer = 0.0;
+        }
+
+        state
+            .interface
+            .lichthupe
+            .set_only_on_change(pressed && timer <= max_duration);
+
+        wait::next_tick().await;
+    }
+}
+
 fn outside_lights(state: &Interface) {
     let cockpit_a_active = state.interface.cockpit_a_active.clone();
+    let lichthupe = state.interface.lichthupe.clone();
     let switch_aussen = state.systems.cockpit.beleuchtung_aussen.clone();
 
-    let richtungswender = state.systems.cockpit.richtungswender.clone();
-    let sollwertgeber = state.systems.cockpit.sollwertgeber.clone();
-
     let instrumente = state.systems.lights.instrumente.clone();
+    let parking = state.systems.startup.parking.clone();
     let lm_fernlicht = state.systems.cockpit.lm_fernlicht.clone();
 
-    let standlicht = state.systems.lights.stand.clone();
-    let ruecklicht = state.systems.lights.rueck.clone();
-    let abblend = state.systems.lights.abblend.clone();
-    let fern = state.systems.lights.fern.clone();
-    let rueckfahr = state.systems.lights.rueckfahr.clone();
-    let brems = state.systems.lights.brems.clone();
+    let end_a = state.systems.lights.end_a.clone();
+    let end_b = state.systems.lights.end_b.clone();
+    let parking_pattern = state.systems.lights.parking_pattern;
+    let coupled_a = state.systems.coupling.coupled_a.clone();
+    let coupled_b = state.systems.coupling.coupled_b.clone();
+    let remote_lighting = state.systems.coupling.remote_lighting.clone();
+    let role = state.systems.coupling.role.clone();
 
     multiple_on_change(
-        &[&switch_aussen.clone(), &cockpit_a_active.clone()],
+        &[
+            &switch_aussen.clone(),
+            &cockpit_a_active.clone(),
+            &role.clone(),
+            &coupled_a.clone(),
+            &coupled_b.clone(),
+            &remote_lighting.clone(),
+            &parking.clone(),
+            &lichthupe.clone(),
+        ],
         move || {
             let active = cockpit_a_active.get();
             let switch_aussen = switch_aussen.get();
             let switch_standlicht = switch_aussen != OutsideLightSwitch::Off;
-            let switch_abblend = (switch_aussen == OutsideLightSwitch::Abblend)
-                || (switch_aussen == OutsideLightSwitch::Fern);
-            let switch_fern = switch_aussen == OutsideLightSwitch::Fern;
+            let switch_fern = switch_aussen == OutsideLightSwitch::Fern || lichthupe.get();
+
+            instrumente.set(switch_standlicht && !parking.get());
+
+            let remote_tail_lights = remote_lighting.get().map(|sync| sync.tail_lights);
+
+            // Die Rolle im Zugverband bestimmt Stirn- und Schlusslichter
+            for (end, lights, coupled) in [
+                (VehicleEnd::A, &end_a, coupled_a.get()),
+                (VehicleEnd::B, &end_b, coupled_b.get()),
+            ] {
+                lights.set(end_lights_target(
+                    end,
+                    coupled,
+                    role.get(),
+                    switch_aussen,
+                    lichthupe.get(),
+                    remote_tail_lights,
+                    parking_pattern,
+                ));
+            }
 
-            log::info!("switch_abblend: {}", switch_abblend);
+            lm_fernlicht.set(switch_fern && active);
+        },
+    );
+}
 
-            standlicht.set(switch_standlicht);
-            ruecklicht.set(switch_standlicht);
-            instrumente.set(switch_standlicht);
+fn brake_lights(state: &Interface) {
+    let brake_force = state.systems.traction.brake_force.clone();
+    let hazard_brake = state.systems.traction.hazard_brake.clone();
+    let direction = state.systems.traction.direction.clone();
+    let remote_lighting = state.systems.coupling.remote_lighting.clone();
 
-            abblend.set(switch_abblend && active);
-            fern.set(switch_fern && active);
-            lm_fernlicht.set(switch_fern && active);
-            rueckfahr.set(richtungswender.get() == RichtungswenderState::R);
-            brems.set(sollwertgeber.get() < 0.0);
+    let brems = state.systems.lights.brems.clone();
+    let rueckfahr = state.systems.lights.rueckfahr.clone();
+    let gefahrbremsung = state.systems.lights.gefahrbremsung.clone();
+
+    multiple_on_change(
+        &[
+            &brake_force.clone(),
+            &hazard_brake.clone(),
+            &direction.clone(),
+            &remote_lighting.clone(),
+        ],
+        move || {
+            if let Some(sync) = remote_lighting.get() {
+                brems.set_only_on_change(sync.brems);
+                rueckfahr.set_only_on_change(sync.rueckfahr);
+                gefahrbremsung.set_only_on_change(sync.gefahrbremsung);
+            } else {
+                brems.set_only_on_change(brake_force.get() > BRAKE_LIGHT_MIN_FORCE);
+                rueckfahr.set_only_on_change(direction.get() == TractionDirection::Backward);
+                gefahrbremsung.set_only_on_change(hazard_brake.get());
+            }
         },
     );
 }
 
+/// Switches the hazard lights on after a passenger emergency brake, a hazard braking,
+/// a door emergency release or a collision. Pressing the hazard button cancels it.
+async fn auto_hazard_lights(state: Interface) {
+    let traction = &state.systems.traction;
+    let warn_auto = &state.systems.lights.warn_auto;
+    let warn_manual = &state.systems.lights.warn_manual;
+
+    let mut prev_event = false;
+    let mut prev_button = state.systems.cockpit.warnblinker.get().is_in();
+    let mut button_ignored = false;
+
+    loop {
+        let event = traction.emergency_brake.get()
+            || traction.hazard_brake.get()
+            || traction.collision.get()
+            || state.systems.doors.emergency_released.get();
+
+        let button = state.systems.cockpit.warnblinker.get().is_in();
+
+        if event && !prev_event {
+            log::info!("Warnblinker automatisch eingeschaltet");
+            warn_auto.set_only_on_change(true);
+        } else if button != prev_button {
+            // Der Tastendruck quittiert nur den automatischen Warnblinker, die Taste zählt
+            // erst wieder beim nächsten Drücken
+            button_ignored = warn_auto.get();
+            warn_auto.set_only_on_change(false);
+        }
+
+        warn_manual.set_only_on_change(button && !button_ignored);
+
+        prev_event = event;
+        prev_button = button;
+
+        wait::next_tick().await;
+    }
+}
+
+/// Returns the indicator switch to off after `Cfg_BlinkerCancelDistance` metres, or with
+/// `Cfg_BlinkerCancelAfterSwitch` once the vehicle has passed through a switch. Both are off by default.
+async fn blinker_self_cancel(state: Interface) {
+    let cancel_distance = get_var::<f32>("Cfg_BlinkerCancelDistance");
+    let cancel_after_switch = get_var::<i32>("Cfg_BlinkerCancelAfterSwitch") != 0;
+
+    let switch_blinker = &state.systems.cockpit.blinker;
+
+    let rail_quality_vars =
+        [(0, 0), (0, 1), (2, 0), (2, 1)].map(|(bogie, axle)| format!("railquality_{bogie}_{axle}"));
+
+    let in_switch = || {
+        rail_quality_vars
+            .iter()
+            .any(|var| SWITCH_RAIL_QUALITY.contains(&get_var::<i32>(var.as_str())))
+    };
+
+    let mut distance = 0.0;
+    let mut switch_entered = false;
+    let mut prev_in_switch = false;
+
+    loop {
+        let blinking = switch_blinker.get() != BlinkerSwitch::Off;
+        // Die Gleislage wird nur gebraucht, solange geblinkt wird
+        let in_switch = blinking && cancel_after_switch && in_switch();
+
+        if blinking {
+            distance += state.systems.traction.speed.get().abs() * delta();
+            switch_entered |= in_switch;
+
+            let distance_passed = cancel_distance > 0.0 && distance >= cancel_distance;
+            // Erst wenn kein Drehgestell mehr in der Weiche steht
+            let switch_passed =
+                cancel_after_switch && switch_entered && prev_in_switch && !in_switch;
+
+            if distance_passed || switch_passed {
+                switch_blinker.set(BlinkerSwitch::Off);
+            }
+        } else {
+            distance = 0.0;
+            switch_entered = false;
+        }
+
+        prev_in_switch = in_switch;
+
+        wait::next_tick().await;
+    }
+}
+
 fn blinker_lights(state: &Interface) {
     let cockpit_a_active = state.interface.cockpit_a_active.clone();
-    let switch_warnblinker = state.systems.cockpit.warnblinker.clone();
+    let warn_manual = state.systems.lights.warn_manual.clone();
     let switch_blinker = state.systems.cockpit.blinker.clone();
     let lm_blinker_links = state.systems.cockpit.lm_blinker_links.clone();
     let lm_blinker_rechts = state.systems.cockpit.lm_blinker_rechts.clone();
     let lm_warnblinker = state.systems.cockpit.lm_warnblinker.clone();
 
     let blinker_state = state.systems.lights.blinker_state.clone();
+    let warn_auto = state.systems.lights.warn_auto.clone();
+    let remote_lighting = state.systems.coupling.remote_lighting.clone();
 
     multiple_on_change(
         &[
             &cockpit_a_active.clone(),
-            &switch_warnblinker.clone(),
+            &warn_manual.clone(),
             &switch_blinker.clone(),
+            &remote_lighting.clone(),
+            &warn_auto.clone(),
         ],
         move || {
-            blinker_state.set(if switch_warnblinker.get().is_in() {
+            blinker_state.set(if let Some(sync) = remote_lighting.get() {
+                sync.blinker
+            } else if warn_manual.get() || warn_auto.get() {
                 BlinkerState::Warn
             } else if cockpit_a_active.clone().get() {
                 match switch_blinker.get() {
@@ -303,10 +956,29 @@ fn blinker_lights(state: &Interface) {
         });
 }
 
+async fn lighting_sync(lights: LightState, cockpit: CockpitState, coupling: CouplingState) {
+    loop {
+        if coupling.lead.get() {
+            coupling.lighting.set_only_on_change(LightingSync {
+                tail_lights: cockpit.beleuchtung_aussen.get() != OutsideLightSwitch::Off,
+                brems: lights.brems.get(),
+                rueckfahr: lights.rueckfahr.get(),
+                gefahrbremsung: lights.gefahrbremsung.get(),
+                blinker: lights.blinker_state.get(),
+            });
+        }
+
+        wait::next_tick().await;
+    }
+}
+
 fn inside_lights(state: &Interface) {
     let cockpit_main = state.systems.lights.cockpit_main.clone();
     let cockpit_begleiter = state.systems.lights.cockpit_begleiter.clone();
     let fahrgastraum = state.systems.lights.fahrgastraum.clone();
+    let notbeleuchtung = state.systems.lights.notbeleuchtung.clone();
+    let switch_fahrgastraum = state.systems.cockpit.beleuchtung_fahrgastraum.clone();
+    let parking = state.systems.startup.parking.clone();
 
     state
         .systems
@@ -317,13 +989,27 @@ fn inside_lights(state: &Interface) {
             cockpit_begleiter.set(*active >= 1);
         });
 
-    state
+    multiple_on_change(
+        &[&switch_fahrgastraum.clone(), &parking.clone()],
+        move || {
+            let requested = switch_fahrgastraum.get() && !parking.get();
+            fahrgastraum.set(requested);
+            notbeleuchtung.set(requested);
+        },
+    );
+
+    // Die Türraumbeleuchtung brennt, solange die Tür nicht geschlossen ist
+    for (door, tuerraum) in state
         .systems
-        .cockpit
-        .beleuchtung_fahrgastraum
-        .on_refresh(move |active| {
-            fahrgastraum.set(*active);
-        });
+        .doors
+        .doors_with_controller
+        .iter()
+        .zip(state.systems.lights.tuerraum.iter())
+    {
+        door.closed
+            .process(|closed| !*closed, false)
+            .forward(tuerraum);
+    }
 }
 
 async fn door_control(
@@ -331,8 +1017,13 @@ async fn door_control(
     cockpit: CockpitState,
     passenger: PassengerElementsState,
     traction: TractionState,
+    ramp: RampState,
+    coupling: CouplingState,
 ) {
     let mut prev_switch_door_1 = false;
+    let mut prev_released = false;
+    let mut door_1_toggle = DoorControlMode::Automatic;
+    let mut auto_release = AutoRelease::default();
 
     let shared_doors_closed = Shared::<bool>::default();
 
@@ -344,35 +1035,125 @@ async fn door_control(
         let speed = traction.speed.get();
         let door_switch = cockpit.tueren.get();
         let doors_closed = doors.all_closed.get();
-        let released =
-            (door_switch == DoorSwitch::Released || door_switch == DoorSwitch::Open) && speed < 1.0;
+        let release = doors.release_properties;
+
+        if speed.abs() > release.revoke_speed {
+            doors.at_stop.set_only_on_change(false);
+        }
+
+        // Automatische Freigabe im Stillstand an der Haltestelle oder bei Haltewunsch
+        let auto_release_condition = cockpit.tueren_automatik.get()
+            && (doors.at_stop.get() || doors.stop_request.get())
+            && speed.abs() < release.auto_release_speed;
+
+        // Mit dem Haltewunsch hält das Fahrzeug an einer Haltestelle
+        if auto_release_condition {
+            doors.at_stop.set_only_on_change(true);
+        }
+
+        let auto_released = auto_release.update(
+            auto_release_condition,
+            doors.at_stop.get(),
+            cockpit.zwangsschliessen.get(),
+            door_switch == DoorSwitch::Closed,
+        );
+
+        let switch_released =
+            door_switch == DoorSwitch::Released || door_switch == DoorSwitch::Open;
+
+        // Ohne aktiven Führerstand folgt das Fahrzeug dem führenden Fahrzeug im Zugverband
+        let remote = coupling.remote_door_command.get();
+
+        let released = if let Some(command) = remote {
+            command.released
+        } else if prev_released {
+            (switch_released || auto_released) && speed.abs() < release.revoke_speed
+        } else {
+            (switch_released && speed.abs() < release.release_speed) || auto_released
+        };
 
         // Setze alle Status in einem Block
         let states = {
-            let all_request = released && door_switch == DoorSwitch::Open;
+            let all_request = released
+                && remote.map_or(door_switch == DoorSwitch::Open, |command| command.open_all);
             let switch_door_1 = door_switch == DoorSwitch::Tuer1;
             (released, all_request, switch_door_1)
         };
 
+        let force_close_all =
+            cockpit.zwangsschliessen.get() || remote.is_some_and(|command| command.force_close);
+
+        let mut stop_request = doors.stop_request.get() && !released;
+
         set_var("Door_BtnLgt_Frei", &released);
 
-        for (i, (button, request)) in passenger
-            .door_buttons
-            .iter()
-            .zip(doors.requests.iter())
-            .enumerate()
-        {
-            let button_pressed = button.get();
+        for (i, request) in doors.requests.iter().enumerate() {
+            let pressed_inside =
+                passenger.door_buttons_inside[i].get() || passenger.door_buttons[i].get();
+            let pressed_outside = passenger.door_buttons_outside[i].get();
+            let button_pressed = pressed_inside || pressed_outside;
+
             set_var(
                 format!("Door_{}_BtnLgt_Pressed", i + 1).as_str(),
                 &button_pressed,
             );
-            request.set_only_on_change(states.1 || (button_pressed && released));
+
+            // Ohne Freigabe gilt der Außentaster als Haltewunsch
+            stop_request |= pressed_outside && !released;
+
+            // Wartende Fahrgäste fordern die Tür an
+            let door = &doors.doors_with_controller[i];
+            let passengers_waiting =
+                door.passengers_boarding.get() > 0 || door.passengers_alighting.get() > 0;
+            let ramp_requested = i == RAMP_DOOR && ramp.requested.get();
+
+            request.set_only_on_change(
+                states.1 || ((button_pressed || passengers_waiting || ramp_requested) && released),
+            );
+
+            let button_light = |pressed: bool| {
+                if door.emergency_release.get() {
+                    DoorButtonLight::OutOfService
+                } else if doors.door_overrides[i].get() == DoorControlMode::Close {
+                    DoorButtonLight::Locked
+                } else if released && (pressed || (request.get() && door.closed.get())) {
+                    DoorButtonLight::Acknowledged
+                } else if released {
+                    DoorButtonLight::Released
+                } else {
+                    DoorButtonLight::Off
+                }
+            };
+
+            set_var(
+                format!("Door_{}_BtnLgtI", i + 1).as_str(),
+                &(button_light(pressed_inside) as i32),
+            );
+            set_var(
+                format!("Door_{}_BtnLgtO", i + 1).as_str(),
+                &(button_light(pressed_outside) as i32),
+            );
+
+            set_var(
+                format!("Door_{}_LightBarrier", i + 1).as_str(),
+                &door.light_barrier.get(),
+            );
+
+            // Wer bei der Abfahrt nicht durch die Tür gekommen ist, bleibt zurück
+            if prev_released && !released {
+                door.passengers_boarding.set(0);
+                door.passengers_alighting.set(0);
+            }
+
+            doors.force_close[i]
+                .set_only_on_change(force_close_all || cockpit.zwangsschliessen_tuer[i].get());
         }
 
         doors.released.set_only_on_change(released);
+        doors.open_all.set_only_on_change(states.1);
+        doors.stop_request.set_only_on_change(stop_request);
 
-        shared_doors_closed.set(!released && doors_closed);
+        shared_doors_closed.set(!released && doors_closed && coupling.remote_doors_closed.get());
 
         // if prev_doors_closed != lm_green && lm_green {
         //     true.set("Snd_CP_A_DoorsClosed");
@@ -382,20 +1163,115 @@ async fn door_control(
         doors.vehicle_speed.set_only_on_change(speed);
 
         if !prev_switch_door_1 && states.2 {
-            doors
-                .door_1_override
-                .set_only_on_change(match doors.door_1_override.get() {
-                    DoorControlMode::Automatic => DoorControlMode::Open,
-                    DoorControlMode::Open => DoorControlMode::Close,
-                    DoorControlMode::Close => DoorControlMode::Open,
-                });
+            door_1_toggle = match door_1_toggle {
+                DoorControlMode::Automatic => DoorControlMode::Open,
+                DoorControlMode::Open => DoorControlMode::Close,
+                DoorControlMode::Close => DoorControlMode::Open,
+            };
         } else if released {
-            doors
-                .door_1_override
-                .set_only_on_change(DoorControlMode::Automatic);
+            door_1_toggle = DoorControlMode::Automatic;
+        }
+
+        let door_1_mode = remote.map_or(door_1_toggle, |command| command.door_1_override.into());
+
+        coupling.door_command.set_only_on_change(DoorCommand {
+            released,
+            open_all: states.1,
+            force_close: force_close_all,
+            door_1_override: door_1_toggle.into(),
+        });
+        coupling.doors_closed.set_only_on_change(doors_closed);
+
+        for (i, door_override) in doors.door_overrides.iter().enumerate() {
+            door_override.set_only_on_change(match cockpit.tuer_modus[i].get() {
+                DoorModeSwitch::Open => DoorControlMode::Open,
+                DoorModeSwitch::Close => DoorControlMode::Close,
+                DoorModeSwitch::Automatic if i == 0 => door_1_mode,
+                DoorModeSwitch::Automatic => DoorControlMode::Automatic,
+            });
         }
 
         prev_switch_door_1 = states.2;
+        prev_released = released;
+
+        set_var("Passengers", &(doors.passengers_on_board.get() as i32));
+
+        wait::next_tick().await;
+    }
+}
+
+async fn door_emergency_release(
+    doors: DoorsState,
+    cockpit: CockpitState,
+    passenger: PassengerElementsState,
+) {
+    loop {
+        let reset = cockpit.notentriegelung_reset.get();
+
+        for (i, door) in doors.doors_with_controller.iter().enumerate() {
+            let handle_pulled = passenger.emergency_handles_inside[i].get()
+                || passenger.emergency_handles_outside[i].get();
+
+            // Die Notentriegelung bleibt aktiv, bis der Fahrer sie bei zurückgestelltem Griff quittiert
+            if handle_pulled {
+                if !door.emergency_release.get() {
+                    log::info!("Notentriegelung Tür {}", i + 1);
+                }
+                door.emergency_release.set_only_on_change(true);
+            } else if reset {
+                door.emergency_release.set_only_on_change(false);
+            }
+        }
+
+        doors.emergency_released.set_only_on_change(
+            doors
+                .doors_with_controller
+                .iter()
+                .any(|door| door.emergency_release.get()),
+        );
+
+        wait::next_tick().await;
+    }
+}
+
+async fn wheelchair_ramp(
+    doors: DoorsState,
+    cockpit: CockpitState,
+    passenger: PassengerElementsState,
+    ramp: RampState,
+) {
+    let mut prev_driver_button = false;
+    let mut prev_released = false;
+
+    loop {
+        let door = &doors.doors_with_controller[RAMP_DOOR];
+        let released = doors.released.get();
+
+        // Die Anforderung des Fahrgasts wird im Fahrerstand bis zur Quittierung angezeigt
+        if passenger.wheelchair_request.get() {
+            ramp.requested.set_only_on_change(true);
+            cockpit.lm_rollstuhl.set_only_on_change(true);
+        } else if cockpit.rollstuhl.get() {
+            cockpit.lm_rollstuhl.set_only_on_change(false);
+        }
+
+        let driver_button = cockpit.rampe.get();
+        if driver_button && !prev_driver_button {
+            ramp.requested.set(!ramp.requested.get());
+        }
+        prev_driver_button = driver_button;
+
+        if prev_released && !released {
+            ramp.requested.set_only_on_change(false);
+        }
+        prev_released = released;
+
+        ramp.deploy.set_only_on_change(
+            ramp.requested.get()
+                && released
+                && door.phase.get() == DoorPhase::Open
+                && !door.closed.get(),
+        );
 
         wait::next_tick().await;
     }
@@ -11,12 +11,19 @@ use lotus_rt_extra::{
         TractionUnitMode,
     },
 };
-use lotus_script::var::{get_var, set_var};
+use lotus_script::{
+    time::delta,
+    var::{get_var, set_var},
+};
 
 const VMAX: f32 = 60.0 / 3.6;
 const VMAX_BACK: f32 = 15.0 / 3.6;
 const V_EBRAKE_LIMIT: f32 = 5.0 / 3.6;
 const MAXBRAKEFORCE_N: f32 = 16_000.0;
+/// Deceleration beyond anything the brakes can achieve, taken as a collision
+const COLLISION_DECELERATION: f32 = 6.0;
+/// Time constant of the deceleration filter, so a single jump of the speed does not count
+const COLLISION_FILTER_TIME: f32 = 0.2;
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum TractionDirection {
@@ -33,6 +40,41 @@ pub struct TractionState {
     pub speed: Shared<f32>,
     pub mg: Shared<bool>,
     pub sanding: Shared<bool>,
+    /// Traction interlock: while set, no tractive effort is applied. Braking stays available.
+    pub blocked: Shared<bool>,
+    /// Combined effort of electric, hydraulic, spring and rail brake, 0.0 to 1.0
+    pub brake_force: Shared<f32>,
+    /// Gefahrbremsung: maximum braking requested
+    pub hazard_brake: Shared<bool>,
+    /// Passenger emergency brake pulled
+    pub emergency_brake: Shared<bool>,
+    /// Set while the vehicle decelerates harder than the brakes can
+    pub collision: Shared<bool>,
+    /// Low-voltage supply of the Mg brake, fed by the Bordnetz
+    pub voltage: Shared<f32>,
+    /// Line voltage behind the main switch
+    pub line_voltage: Shared<f32>,
+}
+
+/// Detects a collision from the deceleration of the vehicle, filtered over several ticks
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct CollisionDetector {
+    prev_speed: Option<f32>,
+    deceleration: f32,
+}
+
+impl CollisionDetector {
+    pub fn update(&mut self, speed: f32, delta: f32) -> bool {
+        if delta > 0.0 {
+            let prev_speed = self.prev_speed.replace(speed).unwrap_or(speed);
+            let deceleration = (prev_speed.abs() - speed.abs()) / delta;
+
+            self.deceleration +=
+                (deceleration - self.deceleration) * (delta / COLLISION_FILTER_TIME).min(1.0);
+        }
+
+        self.deceleration > COLLISION_DECELERATION
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -50,6 +92,13 @@ pub fn add_traction() -> TractionState {
         mg: Shared::new(false),
         speed: Shared::new(0.0),
         sanding: Shared::new(false),
+        blocked: Shared::new(false),
+        brake_force: Shared::new(0.0),
+        hazard_brake: Shared::new(false),
+        emergency_brake: Shared::new(false),
+        collision: Shared::new(false),
+        voltage: Shared::new(1.0),
+        line_voltage: Shared::new(0.0),
     };
 
     let traction_mode = Shared::new(TractionUnitMode::Off);
@@ -72,16 +121,14 @@ pub fn add_traction() -> TractionState {
                 .set_wheelspeed(wheelspeed.clone())
                 .set_target_force(target_force.clone())
                 .set_traction_mode(traction_mode.clone())
-                .set_source_voltage(Shared::new(1.0))
+                .set_source_voltage(state.line_voltage.clone())
                 .build(),
         );
 
-        let voltage = Shared::new(1.0f32);
-
         mg_relay
             .delay_relay(0.14, 0.14)
             .to_float()
-            .multiply(&voltage)
+            .multiply(&state.voltage)
             .rail_brake(
                 RailBrakeProperties::builder()
                     .reference_force(128_000.0)
@@ -171,21 +218,30 @@ pub fn add_traction() -> TractionState {
         let richtungswender = state.direction.clone();
         let sollwertgeber = state.target.clone();
         let federspeicher = state.federspeicher.clone();
+        let blocked = state.blocked.clone();
+        let mg = state.mg.clone();
+        let brake_force = state.brake_force.clone();
+        let hazard_brake = state.hazard_brake.clone();
+        let emergency_brake = state.emergency_brake.clone();
+        let collision = state.collision.clone();
 
         spawn(async move {
             let mut mode_fixed = true;
 
             let mut prev_speed = 0.0;
+            let mut collision_detector = CollisionDetector::default();
 
             loop {
                 let richtungswender = richtungswender.get();
                 let sollwertgeber = sollwertgeber.get();
 
                 let fast_brake = sollwertgeber < -0.95;
-                let emergency_brake = false;
+                let emergency_brake = emergency_brake.get();
 
                 let max_brake = fast_brake || emergency_brake;
 
+                let traction_blocked = blocked.get();
+
                 let schleuderschutz_active = false;
                 let gleitschutz_active = false;
 
@@ -206,6 +262,7 @@ pub fn add_traction() -> TractionState {
                 } else if (!reversed && speed_in_dir > VMAX)
                     || (reversed && speed_in_dir > VMAX_BACK)
                     || schleuderschutz_active
+                    || traction_blocked
                 {
                     0.0
                 } else {
@@ -253,14 +310,13 @@ pub fn add_traction() -> TractionState {
 
                 // Pneumatic Brake ----------------------------------------------------------------
 
-                set_var(
-                    "Snd_BrakeFlirr",
-                    &(if mode == TractionUnitMode::Brake {
-                        traction_units[0].traction_unit.wheel_force.get().abs() / MAXBRAKEFORCE_N
-                    } else {
-                        0.0
-                    }),
-                );
+                let electric_brake = if mode == TractionUnitMode::Brake {
+                    traction_units[0].traction_unit.wheel_force.get().abs() / MAXBRAKEFORCE_N
+                } else {
+                    0.0
+                };
+
+                set_var("Snd_BrakeFlirr", &electric_brake);
 
                 let mut pneu_target = if (mode_fixed && !federspeicher_active) || max_brake {
                     1.0
@@ -276,12 +332,36 @@ pub fn add_traction() -> TractionState {
 
                 hydraulic_brake_target.set_only_on_change(pneu_target);
 
+                // Brake lights ----------------------------------------------------
+
+                let spring_brake = if federspeicher_active { 1.0 } else { 0.0 };
+                let rail_brake = if mg.get() { 1.0 } else { 0.0 };
+
+                brake_force.set_only_on_change(
+                    electric_brake
+                        .max(pneu_target)
+                        .max(spring_brake)
+                        .max(rail_brake)
+                        .min(1.0),
+                );
+                hazard_brake.set_only_on_change(max_brake);
+
                 // Additional sounds --------------------------------------------
 
                 if speed == 0.0 && prev_speed != 0.0 {
                     set_var("Snd_Halteruck", &true);
                 }
 
+                // Collision ------------------------------------------------------
+
+                // Ein einzelnes gleitendes Rad soll nicht zählen, daher die Fahrzeuggeschwindigkeit
+                let vehicle_speed = ["0_0", "0_1", "1_0", "1_1", "2_0", "2_1"]
+                    .iter()
+                    .map(|axle| get_var::<f32>(format!("v_Axle_mps_{axle}").as_str()).abs())
+                    .sum::<f32>()
+                    / 6.0;
+                collision.set_only_on_change(collision_detector.update(vehicle_speed, delta()));
+
                 prev_speed = speed;
 
                 // ----------------------------------------------------------------
@@ -293,3 +373,43 @@ pub fn add_traction() -> TractionState {
 
     state
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_speed_jump_is_no_collision() {
+        let mut detector = CollisionDetector::default();
+
+        detector.update(10.0, 1.0 / 60.0);
+        assert!(!detector.update(9.8, 1.0 / 60.0));
+        assert!((0..60).all(|_| !detector.update(9.8, 1.0 / 60.0)));
+    }
+
+    #[test]
+    fn sustained_hard_deceleration_is_a_collision() {
+        let mut detector = CollisionDetector::default();
+        let mut speed = 10.0;
+
+        detector.update(speed, 1.0 / 60.0);
+        let detected = (0..30).any(|_| {
+            speed -= 10.0 / 60.0;
+            detector.update(speed, 1.0 / 60.0)
+        });
+
+        assert!(detected);
+    }
+
+    #[test]
+    fn full_braking_is_no_collision() {
+        let mut detector = CollisionDetector::default();
+        let mut speed: f32 = 15.0;
+
+        detector.update(speed, 1.0 / 60.0);
+        while speed > 0.0 {
+            speed = (speed - 3.0 / 60.0).max(0.0);
+            assert!(!detector.update(speed, 1.0 / 60.0));
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use std::f32::consts::PI;
+
+use lotus_rt::{spawn, wait};
+use lotus_script::{
+    message::MessageMeta,
+    prelude::MessageType,
+    time::delta,
+    var::{get_var, set_var},
+};
+
+use lotus_rt_extra::shared::Shared;
+
+/// Duration of one sweep out and back
+const CYCLE_TIME_SLOW: f32 = 1.6;
+const CYCLE_TIME_FAST: f32 = 1.0;
+/// Pause between two sweeps in interval mode
+const INTERVAL_PAUSE: f32 = 6.0;
+/// Sweeps after releasing the washer button
+const AFTER_WASH_CYCLES: u32 = 2;
+
+/// Washer fluid reservoir in litres
+const WASHER_CAPACITY: f32 = 4.0;
+const WASHER_FLOW: f32 = 0.05;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum WiperMode {
+    #[default]
+    Aus,
+    Intervall,
+    Langsam,
+    Schnell,
+}
+
+impl From<i8> for WiperMode {
+    fn from(value: i8) -> Self {
+        match value {
+            1 => WiperMode::Intervall,
+            2 => WiperMode::Langsam,
+            3 => WiperMode::Schnell,
+            _ => WiperMode::Aus,
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone)]
+pub struct WiperState {
+    pub mode: Shared<WiperMode>,
+    pub washer: Shared<bool>,
+    pub voltage: Shared<f32>,
+    /// Position of the wiper arm, 0.0 is the park position
+    pub position: Shared<f32>,
+    pub parked: Shared<bool>,
+    /// Washer fluid left in litres. The used fluid is written to `Washer_Fluid_Used`, but it is
+    /// not persisted across sessions. A new session starts from the var at load.
+    pub washer_fluid: Shared<f32>,
+    /// The wiper motor is running
+    pub motor: Shared<bool>,
+    /// The washer pump is running, also when the reservoir is empty
+    pub pump: Shared<bool>,
+}
+
+/// Depot action filling up the washer fluid reservoir
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct WasherRefill;
+
+impl MessageType for WasherRefill {
+    const MESSAGE_META: MessageMeta = MessageMeta::new("gt6n", "washer_refill", None);
+}
+
+impl WiperState {
+    pub fn refill(&self) {
+        self.washer_fluid.set(WASHER_CAPACITY);
+    }
+}
+
+pub fn add_wiper() -> WiperState {
+    let channels = WiperState {
+        voltage: Shared::new(1.0),
+        parked: Shared::new(true),
+        washer_fluid: Shared::new(
+            (WASHER_CAPACITY - get_var::<f32>("Washer_Fluid_Used")).clamp(0.0, WASHER_CAPACITY),
+        ),
+        ..Default::default()
+    };
+    let c = channels.clone();
+
+    spawn(async move {
+        // Fortschritt innerhalb eines Wischzyklus, 0.0 ist die Parkstellung
+        let mut phase: f32 = 0.0;
+        let mut pause = 0.0;
+        let mut after_wash = 0;
+
+        loop {
+            let powered = c.voltage.get() > 0.0;
+            let mode = c.mode.get();
+            let fluid = c.washer_fluid.get();
+
+            // Waschanlage ------------------------------------------
+
+            let pumping = powered && c.washer.get();
+            let spraying = pumping && fluid > 0.0;
+
+            if spraying {
+                c.washer_fluid
+                    .set_only_on_change((fluid - WASHER_FLOW * delta()).max(0.0));
+                after_wash = AFTER_WASH_CYCLES;
+            }
+
+            set_var("Snd_Washer_Pump", &spraying);
+            set_var("Snd_Washer_Pump_Dry", &(pumping && !spraying));
+            set_var("Washer_Spray", &spraying);
+            c.pump.set_only_on_change(pumping);
+
+            set_var("Washer_Fluid", &c.washer_fluid.get());
+            set_var(
+                "Washer_Fluid_Used",
+                &(WASHER_CAPACITY - c.washer_fluid.get()),
+            );
+
+            // Wischer ----------------------------------------------
+
+            let cycle_time = match mode {
+                WiperMode::Schnell => CYCLE_TIME_FAST,
+                _ => CYCLE_TIME_SLOW,
+            };
+
+            let in_cycle = phase > 0.0;
+
+            let start = match mode {
+                _ if spraying || after_wash > 0 => true,
+                WiperMode::Aus => false,
+                WiperMode::Intervall => pause <= 0.0,
+                WiperMode::Langsam | WiperMode::Schnell => true,
+            };
+
+            let running = powered && (in_cycle || start);
+
+            if running {
+                phase += delta() / cycle_time;
+
+                // Zyklus beendet: In der Parkstellung bleibt der Wischer nur, wenn er nicht weiter soll
+                if phase >= 1.0 {
+                    if after_wash > 0 && !spraying {
+                        after_wash -= 1;
+                    }
+
+                    let continues = matches!(mode, WiperMode::Langsam | WiperMode::Schnell)
+                        || spraying
+                        || after_wash > 0;
+
+                    if continues {
+                        phase -= 1.0;
+                    } else {
+                        phase = 0.0;
+                        pause = INTERVAL_PAUSE;
+                    }
+                }
+            } else if mode == WiperMode::Intervall {
+                pause -= delta();
+            }
+
+            let position = (1.0 - (2.0 * PI * phase).cos()) * 0.5;
+
+            let parked = phase == 0.0;
+            if parked && !c.parked.get() {
+                set_var("Snd_Wiper_Park", &true);
+            }
+            c.parked.set_only_on_change(parked);
+            c.position.set_only_on_change(position);
+
+            set_var("A_Wiper", &position);
+
+            // Der Motor klingt im schnellen Gang höher
+            let motor = powered && !parked;
+            c.motor.set_only_on_change(motor);
+            set_var("Snd_Wiper_Motor", &motor);
+            set_var(
+                "Snd_Wiper_Motor_Pitch",
+                &(if motor {
+                    CYCLE_TIME_SLOW / cycle_time
+                } else {
+                    0.0
+                }),
+            );
+
+            wait::next_tick().await;
+        }
+    });
+
+    channels
+}